@@ -0,0 +1,215 @@
+use crate::{Diagram, Directive, Frame, StateId, StateStereoType, TransitionId};
+use std::collections::VecDeque;
+
+impl StateId {
+    /// The machine itself; every other state is a descendant of it.
+    pub fn root() -> Self {
+        StateId(VecDeque::new())
+    }
+
+    fn push(&self, frame: Frame) -> Self {
+        let mut frames = self.0.clone();
+        frames.push_back(frame);
+        StateId(frames)
+    }
+
+    /// The child state `name` of this state.
+    pub fn child(&self, name: impl Into<String>) -> Self {
+        self.push(Frame::State { name: name.into() })
+    }
+
+    /// The `[*]` a transition into this state starts from.
+    pub fn start(&self) -> Self {
+        self.push(Frame::Start)
+    }
+
+    /// The `[*]` a transition out of this state leaves from.
+    pub fn end(&self) -> Self {
+        self.push(Frame::End)
+    }
+
+    pub fn history(&self) -> Self {
+        self.push(Frame::History)
+    }
+
+    pub fn deep_history(&self) -> Self {
+        self.push(Frame::DeepHistory)
+    }
+}
+
+/// Builds a [`Diagram`] without going through diagram text.
+///
+/// Every method records its state and all of that state's ancestors, so
+/// `state_parent`, `state_children`, `state_transition_in`/`out` and
+/// `transition_from`/`to` always agree with each other.
+#[derive(Default, Debug, Clone)]
+pub struct DiagramBuilder {
+    diagram: Diagram,
+}
+
+impl DiagramBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `state` and its ancestors.
+    pub fn state(&mut self, state: &StateId) -> &mut Self {
+        let mut child = state.clone();
+        while let Some(parent) = child.parent() {
+            self.diagram
+                .state_children
+                .entry(parent.clone())
+                .or_default()
+                .insert(child.clone());
+            if self
+                .diagram
+                .state_parent
+                .insert(child, parent.clone())
+                .is_some()
+            {
+                break;
+            }
+            child = parent;
+        }
+        self
+    }
+
+    /// Adds `state` as a composite, even before any of its children are added.
+    pub fn composite(&mut self, state: &StateId) -> &mut Self {
+        self.state(state);
+        self.diagram
+            .state_children
+            .entry(state.clone())
+            .or_default();
+        self
+    }
+
+    /// Marks the children of the composite `state` as concurrent regions.
+    pub fn concurrent(&mut self, state: &StateId) -> &mut Self {
+        self.composite(state);
+        self.diagram
+            .state_children_are_concurrent
+            .insert(state.clone());
+        self
+    }
+
+    /// Adds a transition, and both of its endpoints.
+    pub fn transition(&mut self, from: &StateId, to: &StateId, label: Option<&str>) -> &mut Self {
+        let transition = TransitionId(from.clone(), to.clone(), label.map(String::from));
+        self.state(from).state(to);
+        self.diagram
+            .state_transition_out
+            .entry(from.clone())
+            .or_default()
+            .insert(transition.clone());
+        self.diagram
+            .state_transition_in
+            .entry(to.clone())
+            .or_default()
+            .insert(transition.clone());
+        self.diagram
+            .transition_from
+            .insert(transition.clone(), from.clone());
+        self.diagram.transition_to.insert(transition, to.clone());
+        self
+    }
+
+    pub fn alias(&mut self, state: &StateId, alias: impl Into<String>) -> &mut Self {
+        self.state(state);
+        self.diagram.state_alias.insert(state.clone(), alias.into());
+        self
+    }
+
+    pub fn description(&mut self, state: &StateId, description: impl Into<String>) -> &mut Self {
+        self.state(state);
+        self.diagram
+            .state_description
+            .entry(state.clone())
+            .or_default()
+            .push(description.into());
+        self
+    }
+
+    pub fn stereotype(&mut self, state: &StateId, stereotype: StateStereoType) -> &mut Self {
+        self.state(state);
+        self.diagram
+            .state_stereotype
+            .insert(state.clone(), stereotype);
+        self
+    }
+
+    pub fn state_note(&mut self, state: &StateId, note: impl Into<String>) -> &mut Self {
+        self.state(state);
+        self.diagram
+            .state_note
+            .entry(state.clone())
+            .or_default()
+            .push(note.into());
+        self
+    }
+
+    /// Adds a note to a transition, adding the transition if it is missing.
+    pub fn transition_note(
+        &mut self,
+        from: &StateId,
+        to: &StateId,
+        label: Option<&str>,
+        note: impl Into<String>,
+    ) -> &mut Self {
+        self.transition(from, to, label);
+        self.diagram
+            .transition_note
+            .entry(TransitionId(
+                from.clone(),
+                to.clone(),
+                label.map(String::from),
+            ))
+            .or_default()
+            .push(note.into());
+        self
+    }
+
+    /// Names the generated `node` type of `state`, and its module if it is a composite.
+    pub fn rename(&mut self, state: &StateId, name: impl Into<String>) -> &mut Self {
+        self.state(state);
+        self.diagram.state_rename.insert(state.clone(), name.into());
+        self
+    }
+
+    /// Names the generated `edge` type of every transition labelled `label`.
+    pub fn rename_edge(&mut self, label: impl Into<String>, name: impl Into<String>) -> &mut Self {
+        self.diagram.edge_rename.insert(label.into(), name.into());
+        self
+    }
+
+    /// Adds a `%% @zcsm key value` directive.
+    pub fn directive(&mut self, key: impl Into<String>, value: impl Into<String>) -> &mut Self {
+        self.diagram.directives.push(Directive {
+            key: key.into(),
+            value: value.into(),
+        });
+        self
+    }
+
+    /// Sets the front matter `title`.
+    pub fn title(&mut self, title: impl Into<String>) -> &mut Self {
+        self.diagram.title = Some(title.into());
+        self
+    }
+
+    /// Sets a front matter `config` entry, `key` being its dotted path below `config`.
+    pub fn config(&mut self, key: impl Into<String>, value: impl Into<String>) -> &mut Self {
+        self.diagram.config.insert(key.into(), value.into());
+        self
+    }
+
+    /// Adds a note that is not attached to any state or transition.
+    pub fn note(&mut self, note: impl Into<String>) -> &mut Self {
+        self.diagram.note.push(note.into());
+        self
+    }
+
+    pub fn build(&self) -> Diagram {
+        self.diagram.clone()
+    }
+}
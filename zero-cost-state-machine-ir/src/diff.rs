@@ -0,0 +1,300 @@
+use crate::{Diagram, StateId, TransitionId};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{Display, Formatter};
+
+/// A change between two versions of a diagram, as seen by the generated API.
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone)]
+pub enum Change {
+    StateAdded(StateId),
+    StateRemoved(StateId),
+    /// The state keeps its generated type name.
+    StateRenamed {
+        from: StateId,
+        to: StateId,
+    },
+    /// The state moved into another composite, so its generated type moved module.
+    StateReparented {
+        from: StateId,
+        to: StateId,
+    },
+    EdgeAdded {
+        module: Vec<String>,
+        name: String,
+    },
+    EdgeRemoved {
+        module: Vec<String>,
+        name: String,
+    },
+    TransitionAdded(TransitionId),
+    TransitionRemoved(TransitionId),
+    /// Same source and edge type, different `Path::Target`.
+    TransitionRetargeted {
+        from: TransitionId,
+        to: TransitionId,
+    },
+    /// Same source, edge and target types.
+    TransitionRenamed {
+        from: TransitionId,
+        to: TransitionId,
+    },
+    /// The alias, description, note or stereotype of a state changed.
+    StateAnnotationChanged(StateId),
+    TransitionNoteChanged(TransitionId),
+    NoteChanged,
+}
+
+impl Change {
+    /// Whether code written against the old generated `Path` impls can stop compiling.
+    pub fn is_breaking(&self) -> bool {
+        matches!(
+            self,
+            Change::StateRemoved(_)
+                | Change::StateReparented { .. }
+                | Change::EdgeRemoved { .. }
+                | Change::TransitionRemoved(_)
+                | Change::TransitionRetargeted { .. }
+        )
+    }
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        fn edge(module: &[String], name: &str) -> String {
+            module
+                .iter()
+                .map(String::as_str)
+                .chain(["edge", name])
+                .collect::<Vec<_>>()
+                .join("::")
+        }
+        match self {
+            Change::StateAdded(s) => write!(f, "added state {}", s.display_name()),
+            Change::StateRemoved(s) => write!(f, "removed state {}", s.display_name()),
+            Change::StateRenamed { from, to } => write!(
+                f,
+                "renamed state {} to {}",
+                from.display_name(),
+                to.display_name()
+            ),
+            Change::StateReparented { from, to } => write!(
+                f,
+                "moved state {} to {}",
+                from.display_name(),
+                to.display_name()
+            ),
+            Change::EdgeAdded { module, name } => write!(f, "added edge {}", edge(module, name)),
+            Change::EdgeRemoved { module, name } => {
+                write!(f, "removed edge {}", edge(module, name))
+            }
+            Change::TransitionAdded(t) => write!(f, "added transition {}", t.display_name()),
+            Change::TransitionRemoved(t) => write!(f, "removed transition {}", t.display_name()),
+            Change::TransitionRetargeted { from, to } => write!(
+                f,
+                "retargeted transition {} to {}",
+                from.display_name(),
+                to.1.display_name()
+            ),
+            Change::TransitionRenamed { from, to } => write!(
+                f,
+                "renamed transition {} to {}",
+                from.display_name(),
+                to.display_name()
+            ),
+            Change::StateAnnotationChanged(s) => {
+                write!(f, "changed annotations of state {}", s.display_name())
+            }
+            Change::TransitionNoteChanged(t) => {
+                write!(f, "changed note of transition {}", t.display_name())
+            }
+            Change::NoteChanged => write!(f, "changed floating notes"),
+        }
+    }
+}
+
+/// Result of [`Diagram::diff`].
+#[derive(Default, Debug, Eq, PartialEq, Clone)]
+pub struct DiagramDiff {
+    pub changes: Vec<Change>,
+}
+
+impl DiagramDiff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub fn is_breaking(&self) -> bool {
+        self.changes.iter().any(Change::is_breaking)
+    }
+
+    pub fn breaking(&self) -> impl Iterator<Item = &Change> {
+        self.changes.iter().filter(|c| c.is_breaking())
+    }
+}
+
+impl Display for DiagramDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for change in &self.changes {
+            if change.is_breaking() {
+                writeln!(f, "- {} (breaking)", change)?;
+            } else {
+                writeln!(f, "- {}", change)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Source node type and edge type of a generated `Path` impl, plus the target node type
+/// of a `TryPath` impl.
+type PathKey = (Vec<String>, Option<String>, Option<Vec<String>>);
+
+/// The generated items of a diagram, keyed by their Rust paths.
+struct Api<'a> {
+    states: BTreeMap<Vec<String>, &'a StateId>,
+    edges: BTreeSet<(Vec<String>, String)>,
+    paths: BTreeMap<PathKey, (Vec<String>, &'a TransitionId)>,
+}
+
+impl<'a> Api<'a> {
+    fn new(diagram: &'a Diagram) -> Self {
+        let mut states = BTreeMap::new();
+        for state in diagram.state_parent.keys() {
+            states.entry(diagram.type_path(state)).or_insert(state);
+        }
+        let mut edges = BTreeSet::new();
+        let mut paths = BTreeMap::new();
+        let nondeterministic = diagram.nondeterministic_transitions();
+        for transition in diagram.state_transition_out.values().flatten() {
+            let from = diagram.redirected_from(transition);
+            let to = diagram.redirected_to(transition);
+            let edge = diagram.edge_name(transition);
+            if let Some(edge) = &edge {
+                edges.insert((diagram.module_path(from), edge.clone()));
+            }
+            let guarded = nondeterministic
+                .contains(transition)
+                .then(|| diagram.type_path(to));
+            paths
+                .entry((diagram.type_path(from), edge, guarded))
+                .or_insert((diagram.type_path(to), transition));
+        }
+        Api {
+            states,
+            edges,
+            paths,
+        }
+    }
+}
+
+impl Diagram {
+    /// Compares the generated API of `self` (the old version) against `other` (the new one).
+    ///
+    /// States, edges and transitions are matched by the type names the macro generates for
+    /// them, so a rename that keeps the type name is not breaking and one that changes it is.
+    pub fn diff(&self, other: &Diagram) -> DiagramDiff {
+        let old = Api::new(self);
+        let new = Api::new(other);
+        let mut changes = vec![];
+
+        let mut removed: Vec<_> = old
+            .states
+            .iter()
+            .filter(|(p, _)| !new.states.contains_key(*p))
+            .map(|(p, s)| (p, *s))
+            .collect();
+        let mut added: Vec<_> = new
+            .states
+            .iter()
+            .filter(|(p, _)| !old.states.contains_key(*p))
+            .map(|(p, s)| (p, *s))
+            .collect();
+        removed.retain(|(path, from)| {
+            let moved = added
+                .iter()
+                .position(|(p, to)| p.last() == path.last() && to.0.back() == from.0.back());
+            match moved {
+                Some(i) => {
+                    let (_, to) = added.remove(i);
+                    changes.push(Change::StateReparented {
+                        from: (*from).clone(),
+                        to: to.clone(),
+                    });
+                    false
+                }
+                None => true,
+            }
+        });
+        changes.extend(
+            removed
+                .into_iter()
+                .map(|(_, s)| Change::StateRemoved(s.clone())),
+        );
+        changes.extend(
+            added
+                .into_iter()
+                .map(|(_, s)| Change::StateAdded(s.clone())),
+        );
+        for (path, from) in &old.states {
+            if let Some(to) = new.states.get(path) {
+                if from != to {
+                    changes.push(Change::StateRenamed {
+                        from: (*from).clone(),
+                        to: (*to).clone(),
+                    });
+                }
+                if self.state_alias.get(from) != other.state_alias.get(to)
+                    || self.state_description.get(from) != other.state_description.get(to)
+                    || self.state_note.get(from) != other.state_note.get(to)
+                    || self.state_stereotype.get(from) != other.state_stereotype.get(to)
+                {
+                    changes.push(Change::StateAnnotationChanged((*to).clone()));
+                }
+            }
+        }
+
+        for (module, name) in old.edges.difference(&new.edges) {
+            changes.push(Change::EdgeRemoved {
+                module: module.clone(),
+                name: name.clone(),
+            });
+        }
+        for (module, name) in new.edges.difference(&old.edges) {
+            changes.push(Change::EdgeAdded {
+                module: module.clone(),
+                name: name.clone(),
+            });
+        }
+
+        for (key, (old_target, from)) in &old.paths {
+            match new.paths.get(key) {
+                None => changes.push(Change::TransitionRemoved((*from).clone())),
+                Some((new_target, to)) => {
+                    if old_target != new_target {
+                        changes.push(Change::TransitionRetargeted {
+                            from: (*from).clone(),
+                            to: (*to).clone(),
+                        });
+                    } else if from != to {
+                        changes.push(Change::TransitionRenamed {
+                            from: (*from).clone(),
+                            to: (*to).clone(),
+                        });
+                    }
+                    if self.transition_note.get(from) != other.transition_note.get(to) {
+                        changes.push(Change::TransitionNoteChanged((*to).clone()));
+                    }
+                }
+            }
+        }
+        for (key, (_, to)) in &new.paths {
+            if !old.paths.contains_key(key) {
+                changes.push(Change::TransitionAdded((*to).clone()));
+            }
+        }
+
+        if self.note != other.note {
+            changes.push(Change::NoteChanged);
+        }
+        DiagramDiff { changes }
+    }
+}
@@ -0,0 +1,270 @@
+//! Queries over the flattened hierarchy of a diagram.
+//!
+//! The nodes of the flattened graph are the states that generate a `node` type: simple
+//! states and pseudo-states. A transition into a composite enters its `[*]` Start and a
+//! transition out of a composite leaves from its `[*]` End, exactly as in the generated
+//! `Path` impls. When a composite state is passed to a query, it stands for its Start
+//! when it is being arrived at and for its End when it is being left.
+
+use crate::{Diagram, Frame, StateId, TransitionId};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+impl Diagram {
+    /// Whether `state` has children, and so generates a module rather than a node.
+    pub fn is_composite(&self, state: &StateId) -> bool {
+        self.state_children.contains_key(state)
+    }
+
+    fn child_frame<'a>(&'a self, state: &'a StateId, frame: Frame) -> &'a StateId {
+        self.state_children
+            .get(state)
+            .and_then(|c| c.iter().find(|s| s.0.back() == Some(&frame)))
+            .unwrap_or(state)
+    }
+
+    /// The node a composite is entered through, or `state` itself.
+    pub fn entry<'a>(&'a self, state: &'a StateId) -> &'a StateId {
+        self.child_frame(state, Frame::Start)
+    }
+
+    /// The node a composite is left from, or `state` itself.
+    pub fn exit<'a>(&'a self, state: &'a StateId) -> &'a StateId {
+        self.child_frame(state, Frame::End)
+    }
+
+    /// Edges of the flattened hierarchy: every transition after Start/End redirection, plus
+    /// an unlabelled edge from each history state to the states it can resume.
+    pub(crate) fn flat_edges(&self) -> Vec<(&StateId, &StateId, Option<&TransitionId>)> {
+        let mut edges: Vec<_> = self
+            .state_transition_out
+            .values()
+            .flatten()
+            .map(|t| (self.redirected_from(t), self.redirected_to(t), Some(t)))
+            .collect();
+        for history in self.state_parent.keys() {
+            if !matches!(history.0.back(), Some(Frame::History | Frame::DeepHistory)) {
+                continue;
+            }
+            let siblings = self
+                .state_parent
+                .get(history)
+                .and_then(|p| self.state_children.get(p))
+                .into_iter()
+                .flatten()
+                .filter(|s| matches!(s.0.back(), Some(Frame::State { .. })));
+            for sibling in siblings {
+                edges.push((history, self.entry(sibling), None));
+            }
+        }
+        edges
+    }
+
+    /// States reachable from `from` in the flattened hierarchy, following transitions
+    /// forwards or backwards. `from` is included.
+    pub(crate) fn reach<'a>(
+        &'a self,
+        from: impl IntoIterator<Item = &'a StateId>,
+        forward: bool,
+    ) -> BTreeSet<&'a StateId> {
+        let mut adjacent: BTreeMap<&StateId, Vec<&StateId>> = BTreeMap::new();
+        for (a, b, _) in self.flat_edges() {
+            if forward {
+                adjacent.entry(a).or_default().push(b);
+            } else {
+                adjacent.entry(b).or_default().push(a);
+            }
+        }
+        let mut seen: BTreeSet<&StateId> = BTreeSet::new();
+        let mut queue: VecDeque<&StateId> = from.into_iter().collect();
+        while let Some(state) = queue.pop_front() {
+            if seen.insert(state) {
+                queue.extend(adjacent.get(state).into_iter().flatten());
+            }
+        }
+        seen
+    }
+
+    /// The nodes of the flattened hierarchy.
+    pub fn flat_states(&self) -> BTreeSet<&StateId> {
+        self.state_parent
+            .keys()
+            .filter(|s| !self.is_composite(s))
+            .chain(self.flat_edges().into_iter().flat_map(|(a, b, _)| [a, b]))
+            .collect()
+    }
+
+    /// States entered directly by a transition leaving `state`.
+    pub fn successors(&self, state: &StateId) -> BTreeSet<&StateId> {
+        let state = self.exit(state);
+        self.flat_edges()
+            .into_iter()
+            .filter(|(a, _, _)| *a == state)
+            .map(|(_, b, _)| b)
+            .collect()
+    }
+
+    /// States left directly by a transition entering `state`.
+    pub fn predecessors(&self, state: &StateId) -> BTreeSet<&StateId> {
+        let state = self.entry(state);
+        self.flat_edges()
+            .into_iter()
+            .filter(|(_, b, _)| *b == state)
+            .map(|(a, _, _)| a)
+            .collect()
+    }
+
+    /// States that can be reached by any number of transitions, starting in `state`.
+    pub fn reachable_from<'a>(&'a self, state: &'a StateId) -> BTreeSet<&'a StateId> {
+        self.reach([self.entry(state)], true)
+    }
+
+    /// Whether `to`, or one of its substates, can be reached starting in `from`.
+    pub fn can_reach(&self, from: &StateId, to: &StateId) -> bool {
+        covers(&self.reachable_from(from), to)
+    }
+
+    /// The shortest sequence of transitions from `from` into `to`, if there is one.
+    ///
+    /// Resuming through a history state is not an event, so it does not appear in the path.
+    pub fn shortest_path<'a>(
+        &'a self,
+        from: &'a StateId,
+        to: &'a StateId,
+    ) -> Option<Vec<&'a TransitionId>> {
+        let (from, to) = (self.entry(from), self.entry(to));
+        let mut adjacent: BTreeMap<&StateId, Vec<(&StateId, Option<&TransitionId>)>> =
+            BTreeMap::new();
+        for (a, b, t) in self.flat_edges() {
+            adjacent.entry(a).or_default().push((b, t));
+        }
+        let mut previous: BTreeMap<&StateId, (&StateId, Option<&TransitionId>)> = BTreeMap::new();
+        let mut queue = VecDeque::from([from]);
+        while let Some(state) = queue.pop_front() {
+            if state == to {
+                let mut path = vec![];
+                let mut state = state;
+                while let Some((p, t)) = previous.get(state) {
+                    path.extend(*t);
+                    state = p;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for (next, t) in adjacent.get(state).into_iter().flatten() {
+                if *next != from && !previous.contains_key(next) {
+                    previous.insert(next, (state, *t));
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+
+    /// The innermost state enclosing both `a` and `b`; a state encloses itself.
+    pub fn lowest_common_ancestor(&self, a: &StateId, b: &StateId) -> StateId {
+        StateId(
+            a.0.iter()
+                .zip(b.0.iter())
+                .take_while(|(x, y)| x == y)
+                .map(|(x, _)| x.clone())
+                .collect(),
+        )
+    }
+
+    /// Strongly connected components of the flattened hierarchy, each in `StateId` order,
+    /// ordered by their first state. States on no cycle form components of their own.
+    pub fn strongly_connected_components(&self) -> Vec<BTreeSet<&StateId>> {
+        struct Tarjan<'a> {
+            adjacent: BTreeMap<&'a StateId, Vec<&'a StateId>>,
+            index: BTreeMap<&'a StateId, usize>,
+            low: BTreeMap<&'a StateId, usize>,
+            stack: Vec<&'a StateId>,
+            on_stack: BTreeSet<&'a StateId>,
+            components: Vec<BTreeSet<&'a StateId>>,
+        }
+        impl<'a> Tarjan<'a> {
+            fn visit(&mut self, state: &'a StateId) {
+                let index = self.index.len();
+                self.index.insert(state, index);
+                self.low.insert(state, index);
+                self.stack.push(state);
+                self.on_stack.insert(state);
+                for next in self.adjacent.get(state).cloned().unwrap_or_default() {
+                    if !self.index.contains_key(next) {
+                        self.visit(next);
+                        let low = self.low[state].min(self.low[next]);
+                        self.low.insert(state, low);
+                    } else if self.on_stack.contains(next) {
+                        let low = self.low[state].min(self.index[next]);
+                        self.low.insert(state, low);
+                    }
+                }
+                if self.low[state] == self.index[state] {
+                    let mut component = BTreeSet::new();
+                    while let Some(s) = self.stack.pop() {
+                        self.on_stack.remove(s);
+                        component.insert(s);
+                        if s == state {
+                            break;
+                        }
+                    }
+                    self.components.push(component);
+                }
+            }
+        }
+        let mut tarjan = Tarjan {
+            adjacent: BTreeMap::new(),
+            index: BTreeMap::new(),
+            low: BTreeMap::new(),
+            stack: vec![],
+            on_stack: BTreeSet::new(),
+            components: vec![],
+        };
+        for (a, b, _) in self.flat_edges() {
+            tarjan.adjacent.entry(a).or_default().push(b);
+        }
+        for state in self.flat_states() {
+            if !tarjan.index.contains_key(state) {
+                tarjan.visit(state);
+            }
+        }
+        let mut components = tarjan.components;
+        components.sort();
+        components
+    }
+
+    /// The cycles of the flattened hierarchy: strongly connected components of more than one
+    /// state, and states with a transition back to themselves.
+    pub fn cycles(&self) -> Vec<BTreeSet<&StateId>> {
+        let self_loops: BTreeSet<_> = self
+            .flat_edges()
+            .into_iter()
+            .filter(|(a, b, _)| a == b)
+            .map(|(a, _, _)| a)
+            .collect();
+        self.strongly_connected_components()
+            .into_iter()
+            .filter(|c| c.len() > 1 || c.iter().any(|s| self_loops.contains(s)))
+            .collect()
+    }
+
+    /// Whether nothing in the diagram limits how many times `cycle`, one of [`Self::cycles`],
+    /// is taken: one of its states has a transition back to itself, or the machine cannot
+    /// leave the cycle for its final state.
+    pub fn is_unbounded(&self, cycle: &BTreeSet<&StateId>) -> bool {
+        let self_loop = self
+            .flat_edges()
+            .into_iter()
+            .any(|(a, b, _)| a == b && cycle.contains(a));
+        let root_end = StateId::root().end();
+        let finishing = self.reach([&root_end], false);
+        self_loop || !cycle.iter().any(|s| finishing.contains(s))
+    }
+}
+
+/// Whether `state`, or one of its descendants, is in `set`.
+pub(crate) fn covers(set: &BTreeSet<&StateId>, state: &StateId) -> bool {
+    set.range::<&StateId, _>(state..)
+        .next()
+        .is_some_and(|s| s.0.len() >= state.0.len() && s.0.range(..state.0.len()).eq(&state.0))
+}
@@ -0,0 +1,389 @@
+//! The intermediate representation shared by the diagram parser and the code generator.
+//!
+//! A [`Diagram`] is usually produced by parsing Mermaid or PlantUML text, but it can
+//! also be assembled directly with a [`DiagramBuilder`]:
+//!
+//! ```
+//! use zero_cost_state_machine_ir::{DiagramBuilder, StateId};
+//!
+//! let root = StateId::root();
+//! let state3 = root.child("State3");
+//! let long1 = state3.child("long1");
+//! let diagram = DiagramBuilder::new()
+//!     .transition(&root.start(), &root.child("State1"), None)
+//!     .transition(&root.child("State1"), &state3, Some("Succeeded"))
+//!     .transition(&state3.start(), &long1, None)
+//!     .transition(&long1, &long1, Some("New Data"))
+//!     .transition(&long1, &state3.end(), None)
+//!     .transition(&state3, &root.end(), Some("Aborted"))
+//!     .alias(&long1, "Accumulate Enough Data\nLong State Name")
+//!     .build();
+//! assert_eq!(Some(&root), diagram.state_parent.get(&state3));
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt::{Debug, Display, Formatter};
+
+mod builder;
+mod diff;
+mod graph;
+mod naming;
+#[cfg(feature = "serde")]
+mod repr;
+mod validate;
+
+#[cfg(test)]
+mod tests;
+
+pub use builder::DiagramBuilder;
+pub use diff::{Change, DiagramDiff};
+pub use naming::{edge_name, is_identifier, KEYWORDS};
+pub use validate::{Diagnostic, Rule, Severity};
+
+#[derive(Default, Eq, PartialEq, Ord, PartialOrd, Hash, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "repr::Frame", from = "repr::Frame")
+)]
+pub enum Frame {
+    #[default]
+    Start,
+    End,
+    History,
+    DeepHistory,
+    State {
+        name: String,
+    },
+}
+
+impl Debug for Frame {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+impl Display for Frame {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "frame![")?;
+        match self {
+            Frame::Start => write!(f, "Start")?,
+            Frame::End => write!(f, "End")?,
+            Frame::History => write!(f, "History")?,
+            Frame::DeepHistory => write!(f, "DeepHistory")?,
+            Frame::State { name } => write!(f, "\"{}\"", name)?,
+        }
+        write!(f, "]")?;
+        Ok(())
+    }
+}
+
+#[derive(Default, Eq, PartialEq, Ord, PartialOrd, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Frames {
+    pub frames: VecDeque<Frame>,
+}
+
+impl Debug for Frames {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+impl Display for Frames {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "frames![")?;
+        let mut iter = self.frames.iter();
+        if let Some(frame) = iter.next() {
+            match frame {
+                Frame::Start => write!(f, "Start")?,
+                Frame::End => write!(f, "End")?,
+                Frame::History => write!(f, "History")?,
+                Frame::DeepHistory => write!(f, "DeepHistory")?,
+                Frame::State { name } => write!(f, "\"{}\"", name)?,
+            }
+        }
+        for frame in iter {
+            match frame {
+                Frame::Start => write!(f, ",Start")?,
+                Frame::End => write!(f, ",End")?,
+                Frame::History => write!(f, ",History")?,
+                Frame::DeepHistory => write!(f, ",DeepHistory")?,
+                Frame::State { name } => write!(f, ",\"{}\"", name)?,
+            };
+        }
+        write!(f, "]")?;
+        Ok(())
+    }
+}
+
+#[derive(Default, Eq, PartialEq, Ord, PartialOrd, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StateId(pub VecDeque<Frame>);
+
+impl StateId {
+    /// The enclosing state, or `None` for the root.
+    pub fn parent(&self) -> Option<StateId> {
+        let mut frames = self.0.clone();
+        frames.pop_back().map(|_| StateId(frames))
+    }
+}
+
+impl Debug for StateId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+impl Display for StateId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "state_id![")?;
+        let mut iter = self.0.iter();
+        match iter.next() {
+            Some(Frame::Start) => write!(f, "Start")?,
+            Some(Frame::End) => write!(f, "End")?,
+            Some(Frame::History) => write!(f, "History")?,
+            Some(Frame::DeepHistory) => write!(f, "DeepHistory")?,
+            Some(Frame::State { name }) => write!(f, "\"{}\"", name)?,
+            None => {}
+        }
+        for frame in iter {
+            match frame {
+                Frame::Start => write!(f, ",Start")?,
+                Frame::End => write!(f, ",End")?,
+                Frame::History => write!(f, ",History")?,
+                Frame::DeepHistory => write!(f, ",DeepHistory")?,
+                Frame::State { name } => write!(f, ",\"{}\"", name)?,
+            }
+        }
+        write!(f, "]")?;
+        Ok(())
+    }
+}
+
+#[derive(Default, Eq, PartialEq, Ord, PartialOrd, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TransitionId(pub StateId, pub StateId, pub Option<String>);
+
+impl Debug for TransitionId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+impl Display for TransitionId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "transition_id!{{[")?;
+        let mut iter = self.0 .0.iter();
+        match iter.next() {
+            Some(Frame::Start) => write!(f, "Start")?,
+            Some(Frame::End) => write!(f, "End")?,
+            Some(Frame::History) => write!(f, "History")?,
+            Some(Frame::DeepHistory) => write!(f, "DeepHistory")?,
+            Some(Frame::State { name }) => write!(f, "\"{}\"", name)?,
+            None => {}
+        }
+        for frame in iter {
+            match frame {
+                Frame::Start => write!(f, ",Start")?,
+                Frame::End => write!(f, ",End")?,
+                Frame::History => write!(f, ",History")?,
+                Frame::DeepHistory => write!(f, ",DeepHistory")?,
+                Frame::State { name } => write!(f, ",\"{}\"", name)?,
+            }
+        }
+        write!(f, "]->[")?;
+        let mut iter = self.1 .0.iter();
+        match iter.next() {
+            Some(Frame::Start) => write!(f, "Start")?,
+            Some(Frame::End) => write!(f, "End")?,
+            Some(Frame::History) => write!(f, "History")?,
+            Some(Frame::DeepHistory) => write!(f, "DeepHistory")?,
+            Some(Frame::State { name }) => write!(f, "\"{}\"", name)?,
+            None => {}
+        }
+        for frame in iter {
+            match frame {
+                Frame::Start => write!(f, ",Start")?,
+                Frame::End => write!(f, ",End")?,
+                Frame::History => write!(f, ",History")?,
+                Frame::DeepHistory => write!(f, ",DeepHistory")?,
+                Frame::State { name } => write!(f, ",\"{}\"", name)?,
+            }
+        }
+        if let Some(desc) = &self.2 {
+            write!(f, "]:\"{}\"}}", desc)?;
+        } else {
+            write!(f, "]}}")?;
+        }
+        Ok(())
+    }
+}
+
+// Shorthands for writing out ids, matching their `Debug` output. They are exported for the
+// tests of the other crates in the workspace and are not part of the public API.
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! state_id {
+    ($($x:tt)*) => {
+        $crate::StateId($crate::frames![$($x)*].frames)
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! transition_id {
+    ([$($from:tt)*] -> [$($to:tt)*]: $desc:expr) => {
+        $crate::TransitionId(
+            $crate::state_id![$($from)*],
+            $crate::state_id![$($to)*],
+            Some($desc.to_string()),
+        )
+    };
+    ([$($from:tt)*] -> [$($to:tt)*]) => {
+        $crate::TransitionId($crate::state_id![$($from)*], $crate::state_id![$($to)*], None)
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! frame {
+    ($ident:ident) => {{
+        $crate::Frame::$ident
+    }};
+    ($name:expr) => {{
+        $crate::Frame::State {
+            name: String::from($name),
+        }
+    }};
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! frames {
+    // Base case: empty call
+    () => {
+        $crate::Frames {
+            frames: ::std::collections::VecDeque::new(),
+        }
+    };
+
+    ($ident:ident $(, $rest:tt)*) => {
+        {
+            let mut frames = $crate::frames![$($rest),*].frames;
+            frames.push_front($crate::frame![$ident]);
+            $crate::Frames { frames }
+        }
+    };
+
+    ($name:expr $(, $rest:tt)*) => {
+        {
+            let mut frames = $crate::frames![$($rest),*].frames;
+            frames.push_front($crate::frame![$name]);
+            $crate::Frames { frames }
+        }
+    };
+}
+
+#[derive(Default, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum StateStereoType {
+    #[default]
+    Choice,
+    Fork,
+    Join,
+    Other(String),
+}
+
+/// With the `serde` feature enabled, a diagram has a stable serialized form.
+///
+/// The indices kept by `Diagram` (`state_children`, `state_transition_in`/`out`,
+/// `transition_from`/`to`) are derived data, so they are not written out. A diagram is
+/// serialized as a list of states and a list of transitions, both in `StateId` /
+/// `TransitionId` order, and the indices are rebuilt when it is read back:
+///
+/// ```json
+/// {
+///   "states": [
+///     { "id": ["start"] },
+///     { "id": [{ "state": "State3" }], "composite": true },
+///     {
+///       "id": [{ "state": "State3" }, { "state": "long1" }],
+///       "alias": "Accumulate Enough Data\nLong State Name",
+///       "description": ["Just a test"]
+///     }
+///   ],
+///   "transitions": [
+///     {
+///       "from": [{ "state": "State3" }, { "state": "long1" }],
+///       "to": [{ "state": "State3" }, { "state": "long1" }],
+///       "label": "New Data"
+///     }
+///   ],
+///   "note": []
+/// }
+/// ```
+///
+/// A frame is one of `"start"`, `"end"`, `"history"`, `"deep_history"` or
+/// `{ "state": name }`. A stereotype is one of `"choice"`, `"fork"`, `"join"` or
+/// `{ "other": name }`. The optional keys of a state are `alias`, `description`, `note`,
+/// `stereotype`, `composite`, `concurrent` and `rename`; the optional keys of a transition
+/// are `label` and `note`. Absent keys mean the diagram has no entry for that state or
+/// transition. `composite` marks the composite states, so that one without children keeps
+/// its `state_children` entry.
+/// Edge renames are an optional `edge_rename` object from label to name, and directives an
+/// optional `directives` list of `{ "key": key, "value": value }` objects. The front matter
+/// is kept as an optional `title` string and an optional `config` object from dotted path
+/// to value.
+#[derive(Default, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "repr::Diagram", from = "repr::Diagram")
+)]
+pub struct Diagram {
+    pub state_alias: BTreeMap<StateId, String>,
+    pub state_note: BTreeMap<StateId, Vec<String>>,
+    pub state_description: BTreeMap<StateId, Vec<String>>,
+    pub state_stereotype: BTreeMap<StateId, StateStereoType>,
+    pub state_parent: BTreeMap<StateId, StateId>,
+    pub state_children: BTreeMap<StateId, BTreeSet<StateId>>,
+    pub state_children_are_concurrent: BTreeSet<StateId>,
+    pub state_transition_out: BTreeMap<StateId, BTreeSet<TransitionId>>,
+    pub state_transition_in: BTreeMap<StateId, BTreeSet<TransitionId>>,
+    pub transition_from: BTreeMap<TransitionId, StateId>,
+    pub transition_to: BTreeMap<TransitionId, StateId>,
+    pub transition_note: BTreeMap<TransitionId, Vec<String>>,
+    pub note: Vec<String>,
+    /// Names chosen for the generated `node` types (and modules) of states.
+    pub state_rename: BTreeMap<StateId, String>,
+    /// Names chosen for the generated `edge` types of labels.
+    pub edge_rename: BTreeMap<String, String>,
+    /// `%% @zcsm key value` comments, in diagram order, for the code generator to interpret.
+    pub directives: Vec<Directive>,
+    /// The `title` of the front matter.
+    pub title: Option<String>,
+    /// The scalar entries of the front matter `config`, keyed by their dotted path below
+    /// `config`, e.g. `zcsm.lints`.
+    pub config: BTreeMap<String, String>,
+}
+
+/// A `%% @zcsm key value` comment. Mermaid renders it as nothing; the value is the rest of
+/// the line, trimmed.
+#[derive(Default, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Directive {
+    pub key: String,
+    pub value: String,
+}
+
+impl Diagram {
+    pub fn is_empty(&self) -> bool {
+        self.eq(&Self::default())
+    }
+}
@@ -0,0 +1,208 @@
+use crate::{Diagram, Frame, StateId, TransitionId};
+use heck::{ToSnakeCase, ToUpperCamelCase};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Keywords, which the macro emits as raw identifiers such as `r#match`.
+pub const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate",
+    "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl",
+    "in", "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "Self", "static", "struct", "super", "trait", "true", "try", "type",
+    "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+/// Keywords that cannot be raw identifiers.
+const NOT_RAW: &[&str] = &["crate", "self", "Self", "super"];
+
+/// Whether `name` can be written as an identifier, raw if it is one of the [`KEYWORDS`].
+pub fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c == '_' || unicode_ident::is_xid_start(c))
+        && chars.all(unicode_ident::is_xid_continue)
+        && name != "_"
+        && !NOT_RAW.contains(&name)
+}
+
+/// Adjusts an upper camel case name so it can be a type: one starting with a digit, or
+/// `Self`, gets a leading underscore.
+fn escape_type(name: String) -> String {
+    if name.starts_with(|c: char| c.is_ascii_digit()) || NOT_RAW.contains(&name.as_str()) {
+        format!("_{}", name)
+    } else {
+        name
+    }
+}
+
+/// Adjusts a snake case name so it can be a module beside `node` and `edge`: one starting
+/// with a digit gets a leading underscore, and a keyword that cannot be raw, `node` or
+/// `edge` a trailing one.
+fn escape_module(name: String) -> String {
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{}", name)
+    } else if NOT_RAW.contains(&name.as_str()) || name == "node" || name == "edge" {
+        format!("{}_", name)
+    } else {
+        name
+    }
+}
+
+impl Frame {
+    /// Name of the `node` type generated for this frame.
+    pub fn node_name(&self) -> String {
+        match self {
+            Frame::Start => "Start".into(),
+            Frame::End => "End".into(),
+            Frame::History => "History".into(),
+            Frame::DeepHistory => "DeepHistory".into(),
+            Frame::State { name } => escape_type(name.to_upper_camel_case()),
+        }
+    }
+
+    /// The frame as written in the diagram, e.g. `long1` or `[*]`.
+    pub fn display_name(&self) -> &str {
+        match self {
+            Frame::Start | Frame::End => "[*]",
+            Frame::History => "[H]",
+            Frame::DeepHistory => "[H*]",
+            Frame::State { name } => name,
+        }
+    }
+
+    /// Name of the module generated for this frame when it is a composite state.
+    pub fn module_name(&self) -> Option<String> {
+        match self {
+            Frame::State { name } => Some(escape_module(name.to_snake_case())),
+            _ => None,
+        }
+    }
+}
+
+/// Name of the `edge` type generated for a transition label.
+pub fn edge_name(label: &str) -> String {
+    escape_type(label.to_upper_camel_case())
+}
+
+impl StateId {
+    /// Dotted name as written in the diagram, e.g. `State3.long1` or `State3.[*]`.
+    pub fn display_name(&self) -> String {
+        if self.0.is_empty() {
+            return "Root".into();
+        }
+        self.0
+            .iter()
+            .map(Frame::display_name)
+            .collect::<Vec<_>>()
+            .join(".")
+    }
+}
+
+impl TransitionId {
+    /// The transition as written in the diagram, e.g. `State1 --> State2 : Succeeded`.
+    pub fn display_name(&self) -> String {
+        match &self.2 {
+            Some(label) => format!(
+                "{} --> {} : {}",
+                self.0.display_name(),
+                self.1.display_name(),
+                label
+            ),
+            None => format!("{} --> {}", self.0.display_name(), self.1.display_name()),
+        }
+    }
+}
+
+impl Diagram {
+    /// Name of the `node` type generated for `state`, honouring renames.
+    pub fn node_name(&self, state: &StateId) -> Option<String> {
+        match self.state_rename.get(state) {
+            Some(name) => Some(name.clone()),
+            None => state.0.back().map(Frame::node_name),
+        }
+    }
+
+    /// Name of the module generated for `state` when it is a composite, honouring renames.
+    pub fn module_name(&self, state: &StateId) -> Option<String> {
+        match (self.state_rename.get(state), state.0.back()) {
+            (Some(name), Some(Frame::State { .. })) => Some(escape_module(name.to_snake_case())),
+            (_, frame) => frame.and_then(Frame::module_name),
+        }
+    }
+
+    /// Name of a module holding the whole machine, derived from the front matter title.
+    pub fn title_module_name(&self) -> Option<String> {
+        self.title
+            .as_ref()
+            .map(|title| escape_module(title.to_snake_case()))
+    }
+
+    /// Name of the `edge` type generated for a labelled transition, honouring renames.
+    pub fn edge_name(&self, transition: &TransitionId) -> Option<String> {
+        let label = transition.2.as_ref()?;
+        Some(match self.edge_rename.get(label) {
+            Some(name) => name.clone(),
+            None => edge_name(label),
+        })
+    }
+
+    /// Name of the `edge` type generated for an unlabelled transition, when unlabelled
+    /// transitions are named after their target to tell them apart.
+    pub fn completion_edge_name(&self, transition: &TransitionId) -> String {
+        let target = self.node_name(&transition.1);
+        let target = target.unwrap_or_else(|| "Root".into());
+        format!("To{}", target.trim_start_matches('_'))
+    }
+
+    /// Modules enclosing the generated `node` type of `state`, relative to the machine root.
+    pub fn module_path(&self, state: &StateId) -> Vec<String> {
+        (1..state.0.len())
+            .filter_map(|i| self.module_name(&StateId(state.0.range(..i).cloned().collect())))
+            .collect()
+    }
+
+    /// Path of the generated `node` type of `state`, relative to the machine root.
+    pub fn type_path(&self, state: &StateId) -> Vec<String> {
+        let mut path = self.module_path(state);
+        if let Some(name) = self.node_name(state) {
+            path.push("node".into());
+            path.push(name);
+        }
+        path
+    }
+
+    /// The state a transition is generated from: the End state of a composite source.
+    pub fn redirected_from<'a>(&'a self, transition: &'a TransitionId) -> &'a StateId {
+        self.state_children
+            .get(&transition.0)
+            .and_then(|c| c.iter().find(|s| matches!(s.0.back(), Some(Frame::End))))
+            .unwrap_or(&transition.0)
+    }
+
+    /// The state a transition is generated into: the Start state of a composite target.
+    pub fn redirected_to<'a>(&'a self, transition: &'a TransitionId) -> &'a StateId {
+        self.state_children
+            .get(&transition.1)
+            .and_then(|c| c.iter().find(|s| matches!(s.0.back(), Some(Frame::Start))))
+            .unwrap_or(&transition.1)
+    }
+
+    /// Labelled transitions sharing their generated source and `edge` type with a transition
+    /// to a different target. Which of them is taken is decided by a guard.
+    pub fn nondeterministic_transitions(&self) -> BTreeSet<&TransitionId> {
+        let mut groups: BTreeMap<(&StateId, String), Vec<&TransitionId>> = BTreeMap::new();
+        for transition in self.state_transition_out.values().flatten() {
+            if let Some(name) = self.edge_name(transition) {
+                groups
+                    .entry((self.redirected_from(transition), name))
+                    .or_default()
+                    .push(transition);
+            }
+        }
+        groups
+            .into_values()
+            .filter(|g| g.iter().any(|t| t.1 != g[0].1))
+            .flatten()
+            .collect()
+    }
+}
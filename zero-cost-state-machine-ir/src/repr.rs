@@ -0,0 +1,199 @@
+//! Serialized form of the [`Diagram`](crate::Diagram) IR, enabled by the `serde` feature.
+
+use crate::{Diagram as Ir, Directive, Frame as IrFrame, StateId, StateStereoType, TransitionId};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Frame {
+    Start,
+    End,
+    History,
+    DeepHistory,
+    State(String),
+}
+
+impl From<IrFrame> for Frame {
+    fn from(frame: IrFrame) -> Self {
+        match frame {
+            IrFrame::Start => Frame::Start,
+            IrFrame::End => Frame::End,
+            IrFrame::History => Frame::History,
+            IrFrame::DeepHistory => Frame::DeepHistory,
+            IrFrame::State { name } => Frame::State(name),
+        }
+    }
+}
+
+impl From<Frame> for IrFrame {
+    fn from(frame: Frame) -> Self {
+        match frame {
+            Frame::Start => IrFrame::Start,
+            Frame::End => IrFrame::End,
+            Frame::History => IrFrame::History,
+            Frame::DeepHistory => IrFrame::DeepHistory,
+            Frame::State(name) => IrFrame::State { name },
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct State {
+    id: StateId,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    alias: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    description: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    note: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    stereotype: Option<StateStereoType>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    composite: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    concurrent: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rename: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Transition {
+    from: StateId,
+    to: StateId,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    label: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    note: Option<Vec<String>>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Diagram {
+    states: Vec<State>,
+    transitions: Vec<Transition>,
+    #[serde(default)]
+    note: Vec<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    edge_rename: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    directives: Vec<Directive>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    config: BTreeMap<String, String>,
+}
+
+impl From<Ir> for Diagram {
+    fn from(diagram: Ir) -> Self {
+        let states: BTreeSet<&StateId> = diagram
+            .state_parent
+            .keys()
+            .chain(diagram.state_children.keys().filter(|s| !s.0.is_empty()))
+            .chain(diagram.state_alias.keys())
+            .chain(diagram.state_note.keys())
+            .chain(diagram.state_description.keys())
+            .chain(diagram.state_stereotype.keys())
+            .chain(diagram.state_children_are_concurrent.iter())
+            .chain(diagram.state_rename.keys())
+            .collect();
+        let transitions: BTreeSet<&TransitionId> = diagram
+            .transition_from
+            .keys()
+            .chain(diagram.transition_to.keys())
+            .chain(diagram.state_transition_out.values().flatten())
+            .chain(diagram.state_transition_in.values().flatten())
+            .chain(diagram.transition_note.keys())
+            .collect();
+        Diagram {
+            states: states
+                .into_iter()
+                .map(|id| State {
+                    id: id.clone(),
+                    alias: diagram.state_alias.get(id).cloned(),
+                    description: diagram.state_description.get(id).cloned(),
+                    note: diagram.state_note.get(id).cloned(),
+                    stereotype: diagram.state_stereotype.get(id).cloned(),
+                    composite: diagram.state_children.contains_key(id),
+                    concurrent: diagram.state_children_are_concurrent.contains(id),
+                    rename: diagram.state_rename.get(id).cloned(),
+                })
+                .collect(),
+            transitions: transitions
+                .into_iter()
+                .map(|t| Transition {
+                    from: t.0.clone(),
+                    to: t.1.clone(),
+                    label: t.2.clone(),
+                    note: diagram.transition_note.get(t).cloned(),
+                })
+                .collect(),
+            note: diagram.note.clone(),
+            edge_rename: diagram.edge_rename.clone(),
+            directives: diagram.directives.clone(),
+            title: diagram.title.clone(),
+            config: diagram.config.clone(),
+        }
+    }
+}
+
+impl From<Diagram> for Ir {
+    fn from(repr: Diagram) -> Self {
+        let mut diagram = Ir::default();
+        for state in repr.states {
+            let id = state.id;
+            if let Some(parent) = id.parent() {
+                diagram.state_parent.insert(id.clone(), parent.clone());
+                diagram
+                    .state_children
+                    .entry(parent)
+                    .or_default()
+                    .insert(id.clone());
+            }
+            if state.composite {
+                diagram.state_children.entry(id.clone()).or_default();
+            }
+            if let Some(alias) = state.alias {
+                diagram.state_alias.insert(id.clone(), alias);
+            }
+            if let Some(description) = state.description {
+                diagram.state_description.insert(id.clone(), description);
+            }
+            if let Some(note) = state.note {
+                diagram.state_note.insert(id.clone(), note);
+            }
+            if let Some(stereotype) = state.stereotype {
+                diagram.state_stereotype.insert(id.clone(), stereotype);
+            }
+            if let Some(rename) = state.rename {
+                diagram.state_rename.insert(id.clone(), rename);
+            }
+            if state.concurrent {
+                diagram.state_children_are_concurrent.insert(id);
+            }
+        }
+        for transition in repr.transitions {
+            let t = TransitionId(transition.from, transition.to, transition.label);
+            diagram
+                .state_transition_out
+                .entry(t.0.clone())
+                .or_default()
+                .insert(t.clone());
+            diagram
+                .state_transition_in
+                .entry(t.1.clone())
+                .or_default()
+                .insert(t.clone());
+            diagram.transition_from.insert(t.clone(), t.0.clone());
+            diagram.transition_to.insert(t.clone(), t.1.clone());
+            if let Some(note) = transition.note {
+                diagram.transition_note.insert(t, note);
+            }
+        }
+        diagram.note = repr.note;
+        diagram.edge_rename = repr.edge_rename;
+        diagram.directives = repr.directives;
+        diagram.title = repr.title;
+        diagram.config = repr.config;
+        diagram
+    }
+}
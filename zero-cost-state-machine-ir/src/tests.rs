@@ -0,0 +1,21 @@
+use crate::{Diagram, DiagramBuilder, StateId};
+
+mod builder;
+mod diff;
+mod graph;
+mod validate;
+
+/// A machine on the root level, from `[*]` through `(from, to, label)` transitions.
+fn flat(transitions: &[(&str, &str, &str)]) -> Diagram {
+    let root = StateId::root();
+    let mut builder = DiagramBuilder::new();
+    builder.transition(&root.start(), &root.child(transitions[0].0), None);
+    for (from, to, label) in transitions {
+        let to = match *to {
+            "[*]" => root.end(),
+            to => root.child(to),
+        };
+        builder.transition(&root.child(*from), &to, Some(label));
+    }
+    builder.build()
+}
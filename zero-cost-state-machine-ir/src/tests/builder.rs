@@ -0,0 +1,17 @@
+use crate::{DiagramBuilder, StateId};
+use pretty_assertions::assert_eq;
+
+#[test]
+fn empty_composite() {
+    let root = StateId::root();
+    let diagram = DiagramBuilder::new()
+        .composite(&root.child("Outer").child("Inner"))
+        .build();
+    assert_eq!(
+        Some(&root.child("Outer")),
+        diagram
+            .state_parent
+            .get(&root.child("Outer").child("Inner"))
+    );
+    assert!(diagram.state_children[&root.child("Outer").child("Inner")].is_empty());
+}
@@ -0,0 +1,122 @@
+use super::flat;
+use crate::{state_id, transition_id};
+use crate::{Change, DiagramBuilder, StateId};
+use pretty_assertions::assert_eq;
+
+const BASE: &[(&str, &str, &str)] = &[
+    ("State1", "State2", "Succeeded"),
+    ("State1", "[*]", "Aborted"),
+    ("State2", "[*]", "Aborted"),
+];
+
+#[test]
+fn identical() {
+    let diff = flat(BASE).diff(&flat(BASE));
+    assert!(diff.is_empty());
+}
+
+#[test]
+fn added_note_is_not_breaking() {
+    let mut new = flat(BASE);
+    new.state_note
+        .insert(state_id!["State1"], vec!["explained".to_string()]);
+    let diff = flat(BASE).diff(&new);
+    assert_eq!(
+        vec![Change::StateAnnotationChanged(state_id!["State1"])],
+        diff.changes
+    );
+    assert!(!diff.is_breaking());
+}
+
+#[test]
+fn removed_edge_is_breaking() {
+    let new = flat(&[BASE[0], BASE[2]]);
+    let diff = flat(BASE).diff(&new);
+    assert_eq!(
+        vec![Change::TransitionRemoved(
+            transition_id! {["State1"]->[End]:"Aborted"}
+        )],
+        diff.changes
+    );
+    assert!(diff.is_breaking());
+}
+
+#[test]
+fn rename_keeping_type_name_is_not_breaking() {
+    let new = flat(&[
+        ("State1", "state_2", "succeeded"),
+        ("State1", "[*]", "Aborted"),
+        ("state_2", "[*]", "Aborted"),
+    ]);
+    let diff = flat(BASE).diff(&new);
+    assert_eq!(
+        vec![
+            Change::StateRenamed {
+                from: state_id!["State2"],
+                to: state_id!["state_2"],
+            },
+            Change::TransitionRenamed {
+                from: transition_id! {["State1"]->["State2"]:"Succeeded"},
+                to: transition_id! {["State1"]->["state_2"]:"succeeded"},
+            },
+            Change::TransitionRenamed {
+                from: transition_id! {["State2"]->[End]:"Aborted"},
+                to: transition_id! {["state_2"]->[End]:"Aborted"},
+            },
+        ],
+        diff.changes
+    );
+    assert!(!diff.is_breaking());
+}
+
+#[test]
+fn retargeted_is_breaking() {
+    let new = flat(&[
+        ("State1", "State3", "Succeeded"),
+        ("State1", "[*]", "Aborted"),
+        ("State2", "[*]", "Aborted"),
+    ]);
+    let diff = flat(BASE).diff(&new);
+    assert_eq!(
+        "\
+- added state State3
+- retargeted transition State1 --> State2 : Succeeded to State3 (breaking)
+",
+        diff.to_string()
+    );
+}
+
+#[test]
+fn reparented_is_breaking() {
+    let root = StateId::root();
+    let (a, b) = (root.child("A"), root.child("B"));
+    // `A` holds `first` and `B` holds `second`.
+    let build = |first: &str, second: &str| {
+        DiagramBuilder::new()
+            .transition(&root.start(), &a, None)
+            .transition(&a, &b, Some("Next"))
+            .transition(&a.start(), &a.child(first), None)
+            .transition(&a.child(first), &a.end(), None)
+            .transition(&b.start(), &b.child(second), None)
+            .transition(&b.child(second), &b.end(), None)
+            .build()
+    };
+    let diff = build("work", "idle").diff(&build("idle", "work"));
+    assert_eq!(
+        vec![
+            &Change::StateReparented {
+                from: state_id!["A", "work"],
+                to: state_id!["B", "work"],
+            },
+            &Change::StateReparented {
+                from: state_id!["B", "idle"],
+                to: state_id!["A", "idle"],
+            },
+        ],
+        diff.changes
+            .iter()
+            .filter(|c| matches!(c, Change::StateReparented { .. }))
+            .collect::<Vec<_>>()
+    );
+    assert!(diff.is_breaking());
+}
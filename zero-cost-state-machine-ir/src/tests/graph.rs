@@ -0,0 +1,165 @@
+use super::flat;
+use crate::{state_id, transition_id};
+use crate::{Diagram, DiagramBuilder, StateId};
+use pretty_assertions::assert_eq;
+use std::collections::BTreeSet;
+
+/// ```text
+/// stateDiagram-v2
+/// [*] --> State1
+/// State1 --> State2 : Succeeded
+/// State1 --> [*] : Aborted
+/// State2 --> State3 : Succeeded
+/// State2 --> [*] : Aborted
+/// state State3 {
+///   [*] --> long1
+///   long1 --> long1 : New Data
+///   long1 --> ProcessData : Enough Data
+///   ProcessData --> [*]
+/// }
+/// State3 --> State3 : Failed
+/// State3 --> [*] : Succeeded / Save Result
+/// ```
+fn diagram() -> Diagram {
+    let root = StateId::root();
+    let (state1, state2, state3) = (
+        root.child("State1"),
+        root.child("State2"),
+        root.child("State3"),
+    );
+    let (long1, process_data) = (state3.child("long1"), state3.child("ProcessData"));
+    DiagramBuilder::new()
+        .transition(&root.start(), &state1, None)
+        .transition(&state1, &state2, Some("Succeeded"))
+        .transition(&state1, &root.end(), Some("Aborted"))
+        .transition(&state2, &state3, Some("Succeeded"))
+        .transition(&state2, &root.end(), Some("Aborted"))
+        .transition(&state3.start(), &long1, None)
+        .transition(&long1, &long1, Some("New Data"))
+        .transition(&long1, &process_data, Some("Enough Data"))
+        .transition(&process_data, &state3.end(), None)
+        .transition(&state3, &state3, Some("Failed"))
+        .transition(&state3, &root.end(), Some("Succeeded / Save Result"))
+        .build()
+}
+
+#[test]
+fn neighbours_of_composite() {
+    let diagram = diagram();
+    assert_eq!(
+        BTreeSet::from([&state_id![End], &state_id!["State3", Start]]),
+        diagram.successors(&state_id!["State3"])
+    );
+    assert_eq!(
+        BTreeSet::from([&state_id!["State2"], &state_id!["State3", End]]),
+        diagram.predecessors(&state_id!["State3"])
+    );
+}
+
+#[test]
+fn reachability() {
+    let diagram = diagram();
+    assert!(diagram.can_reach(&state_id!["State1"], &state_id!["State3"]));
+    assert!(diagram.can_reach(&state_id!["State3", "long1"], &state_id![End]));
+    assert!(!diagram.can_reach(&state_id!["State3"], &state_id!["State1"]));
+    assert!(!diagram
+        .reachable_from(&state_id!["State2"])
+        .contains(&state_id!["State1"]));
+}
+
+#[test]
+fn shortest_path() {
+    let diagram = diagram();
+    assert_eq!(
+        Some(vec![
+            &transition_id! {["State1"]->["State2"]:"Succeeded"},
+            &transition_id! {["State2"]->["State3"]:"Succeeded"},
+            &transition_id! {["State3", Start]->["State3", "long1"]},
+            &transition_id! {["State3", "long1"]->["State3", "ProcessData"]:"Enough Data"},
+        ]),
+        diagram.shortest_path(&state_id!["State1"], &state_id!["State3", "ProcessData"])
+    );
+    assert_eq!(
+        Some(vec![]),
+        diagram.shortest_path(&state_id!["State1"], &state_id!["State1"])
+    );
+    assert_eq!(
+        None,
+        diagram.shortest_path(&state_id!["State2"], &state_id!["State1"])
+    );
+}
+
+#[test]
+fn lowest_common_ancestor() {
+    let diagram = diagram();
+    assert_eq!(
+        state_id!["State3"],
+        diagram.lowest_common_ancestor(&state_id!["State3", "long1"], &state_id!["State3", End])
+    );
+    assert_eq!(
+        state_id![],
+        diagram.lowest_common_ancestor(&state_id!["State3", "long1"], &state_id!["State1"])
+    );
+}
+
+#[test]
+fn strongly_connected_components() {
+    let diagram = diagram();
+    let components = diagram.strongly_connected_components();
+    assert_eq!(
+        diagram.flat_states().len(),
+        components.iter().map(BTreeSet::len).sum::<usize>()
+    );
+    assert_eq!(
+        vec![&BTreeSet::from([
+            &state_id!["State3", Start],
+            &state_id!["State3", End],
+            &state_id!["State3", "long1"],
+            &state_id!["State3", "ProcessData"],
+        ])],
+        components
+            .iter()
+            .filter(|c| c.len() > 1)
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn cycles() {
+    let diagram = flat(&[
+        ("Idle", "Idle", "Tick"),
+        ("Idle", "Ping", "Start"),
+        ("Ping", "Pong", "Hit"),
+        ("Pong", "Ping", "Hit"),
+        ("Pong", "[*]", "Miss"),
+    ]);
+    assert_eq!(
+        vec![
+            BTreeSet::from([&state_id!["Idle"]]),
+            BTreeSet::from([&state_id!["Ping"], &state_id!["Pong"]]),
+        ],
+        diagram.cycles()
+    );
+}
+
+#[test]
+fn unbounded_cycles() {
+    let diagram = flat(&[
+        ("Idle", "Idle", "Tick"),
+        ("Idle", "Ping", "Start"),
+        ("Ping", "Pong", "Hit"),
+        ("Pong", "Ping", "Hit"),
+        ("Pong", "Rally", "Miss"),
+        ("Pong", "[*]", "Out"),
+        ("Rally", "Volley", "Hit"),
+        ("Volley", "Rally", "Hit"),
+        ("Idle", "[*]", "Stop"),
+    ]);
+    let unbounded: Vec<bool> = diagram
+        .cycles()
+        .iter()
+        .map(|c| diagram.is_unbounded(c))
+        .collect();
+    // The self-loop on Idle, Ping and Pong with a way out, and Rally and Volley without one.
+    assert_eq!(vec![true, false, true], unbounded);
+}
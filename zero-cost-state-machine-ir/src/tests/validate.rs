@@ -0,0 +1,69 @@
+use crate::frame;
+use crate::{edge_name, is_identifier, Diagram, DiagramBuilder, Frame, Rule, StateId, KEYWORDS};
+use pretty_assertions::assert_eq;
+
+fn rules(diagram: &Diagram) -> Vec<(Rule, String)> {
+    diagram
+        .validate()
+        .into_iter()
+        .map(|d| (d.rule, d.message))
+        .collect()
+}
+
+#[test]
+fn pseudo_state_composite() {
+    let root = StateId::root();
+    let diagram = DiagramBuilder::new()
+        .transition(&root.start(), &root.start().child("inner"), None)
+        .build();
+    assert_eq!(
+        vec![
+            (
+                Rule::PseudoStateComposite,
+                "the special state Start cannot have children, but [*] does".to_string()
+            ),
+            (
+                Rule::MissingEnd,
+                "state [*] must contain an End state".to_string()
+            ),
+        ],
+        rules(&diagram)
+    );
+}
+
+#[test]
+fn escaped_names() {
+    assert_eq!("_404NotFound", edge_name("404 Not Found"));
+    assert_eq!("_Self", frame!("self").node_name());
+    assert_eq!(Some("match".into()), frame!("match").module_name());
+    assert!(KEYWORDS.contains(&"match") && is_identifier("match"));
+    assert!(!is_identifier("self"));
+    assert_eq!(Some("self_".into()), frame!("self").module_name());
+    assert_eq!(Some("node_".into()), frame!("Node").module_name());
+    assert_eq!("Größe", frame!("größe").node_name());
+    assert_eq!("[H*]", Frame::DeepHistory.display_name());
+    assert_eq!("long1", frame!("long1").display_name());
+}
+
+#[test]
+fn invalid_names() {
+    let root = StateId::root();
+    let diagram = DiagramBuilder::new()
+        .transition(&root.start(), &root.child("_"), None)
+        .transition(&root.child("_"), &root.end(), Some("!!!"))
+        .build();
+    assert_eq!(
+        vec![
+            (
+                Rule::InvalidName,
+                "state _ cannot be named in Rust: `` is not an identifier".to_string()
+            ),
+            (
+                Rule::InvalidName,
+                "transition _ --> [*] : !!! cannot be named in Rust: `` is not an identifier"
+                    .to_string()
+            ),
+        ],
+        rules(&diagram)
+    );
+}
@@ -0,0 +1,323 @@
+use crate::graph::covers;
+use crate::naming::is_identifier;
+use crate::{Diagram, Frame, StateId};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy)]
+pub enum Severity {
+    Warning,
+    /// The macro refuses to generate code for the diagram.
+    Error,
+}
+
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy)]
+pub enum Rule {
+    /// A composite with incoming transitions has no `[*]` to enter through.
+    MissingStart,
+    /// A composite with outgoing transitions has no `[*]` to leave from.
+    MissingEnd,
+    TransitionOutOfRoot,
+    /// Two children of one state generate the same `node` type.
+    NodeNameCollision,
+    /// Two transitions out of one state into one target generate the same `edge` type.
+    EdgeNameCollision,
+    /// Two unlabelled transitions out of one state both generate `Path<()>`.
+    AmbiguousCompletion,
+    /// A Start, End or history state has children.
+    PseudoStateComposite,
+    /// A state or label converts to something that is not a Rust identifier.
+    InvalidName,
+    /// The state cannot be reached from the root `[*]`.
+    Unreachable,
+    /// The root `[*]` cannot be reached from the state.
+    CannotReachEnd,
+    /// A reachable composite whose own `[*]` cannot be reached from its Start.
+    NeverExited,
+    /// A transition out of a reachable state that can never be taken.
+    DeadTransition,
+    /// Two states are displayed with the same alias.
+    DuplicateAlias,
+}
+
+impl Rule {
+    pub fn severity(&self) -> Severity {
+        match self {
+            Rule::MissingStart
+            | Rule::MissingEnd
+            | Rule::TransitionOutOfRoot
+            | Rule::NodeNameCollision
+            | Rule::EdgeNameCollision
+            | Rule::AmbiguousCompletion
+            | Rule::PseudoStateComposite
+            | Rule::InvalidName => Severity::Error,
+            Rule::Unreachable
+            | Rule::CannotReachEnd
+            | Rule::NeverExited
+            | Rule::DeadTransition
+            | Rule::DuplicateAlias => Severity::Warning,
+        }
+    }
+}
+
+/// A problem found by [`Diagram::validate`].
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone)]
+pub struct Diagnostic {
+    pub rule: Rule,
+    /// The states the problem is about, most relevant first.
+    pub states: Vec<StateId>,
+    pub message: String,
+}
+
+impl Diagnostic {
+    fn new(rule: Rule, states: Vec<StateId>, message: String) -> Self {
+        Diagnostic {
+            rule,
+            states,
+            message,
+        }
+    }
+
+    pub fn severity(&self) -> Severity {
+        self.rule.severity()
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity() == Severity::Error
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+fn describe(state: &StateId) -> String {
+    if state.0.is_empty() {
+        "the special state Root".into()
+    } else {
+        format!("state {}", state.display_name())
+    }
+}
+
+impl Diagram {
+    /// Checks the diagram against the rules the code generator relies on, plus warnings for
+    /// states that can never be entered or never finish and transitions that can never fire.
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = vec![];
+
+        for state in self.state_children.keys() {
+            if let Some(frame @ (Frame::Start | Frame::End | Frame::History | Frame::DeepHistory)) =
+                state.0.back()
+            {
+                diagnostics.push(Diagnostic::new(
+                    Rule::PseudoStateComposite,
+                    vec![state.clone()],
+                    format!(
+                        "the special state {} cannot have children, but {} does",
+                        frame.node_name(),
+                        state.display_name()
+                    ),
+                ));
+            }
+        }
+
+        for state in self.state_parent.keys() {
+            if !matches!(state.0.back(), Some(Frame::State { .. })) {
+                continue;
+            }
+            let module = self.is_composite(state).then(|| self.module_name(state));
+            for name in self.node_name(state).into_iter().chain(module.flatten()) {
+                if !is_identifier(&name) {
+                    diagnostics.push(Diagnostic::new(
+                        Rule::InvalidName,
+                        vec![state.clone()],
+                        format!(
+                            "state {} cannot be named in Rust: `{}` is not an identifier",
+                            state.display_name(),
+                            name
+                        ),
+                    ));
+                }
+            }
+        }
+        for transition in self.state_transition_out.values().flatten() {
+            let Some(name) = self.edge_name(transition) else {
+                continue;
+            };
+            if !is_identifier(&name) {
+                diagnostics.push(Diagnostic::new(
+                    Rule::InvalidName,
+                    vec![transition.0.clone(), transition.1.clone()],
+                    format!(
+                        "transition {} cannot be named in Rust: `{}` is not an identifier",
+                        transition.display_name(),
+                        name
+                    ),
+                ));
+            }
+        }
+
+        for (state, children) in &self.state_children {
+            let has = |f: fn(&Frame) -> bool| children.iter().any(|c| c.0.back().is_some_and(f));
+            if !has(|f| matches!(f, Frame::Start)) && self.state_transition_in.contains_key(state) {
+                diagnostics.push(Diagnostic::new(
+                    Rule::MissingStart,
+                    vec![state.clone()],
+                    format!("{} must contain Start state", describe(state)),
+                ));
+            }
+            if !has(|f| matches!(f, Frame::End)) && self.state_transition_out.contains_key(state) {
+                diagnostics.push(Diagnostic::new(
+                    Rule::MissingEnd,
+                    vec![state.clone()],
+                    format!("{} must contain an End state", describe(state)),
+                ));
+            }
+        }
+
+        if self.state_transition_out.contains_key(&StateId::root()) {
+            diagnostics.push(Diagnostic::new(
+                Rule::TransitionOutOfRoot,
+                vec![StateId::root()],
+                "no transition can lead out of the special state Root".into(),
+            ));
+        }
+
+        for (state, children) in &self.state_children {
+            let mut names: BTreeMap<String, &StateId> = BTreeMap::new();
+            for child in children {
+                let Some(name) = self.node_name(child) else {
+                    continue;
+                };
+                if let Some(other) = names.insert(name.clone(), child) {
+                    diagnostics.push(Diagnostic::new(
+                        Rule::NodeNameCollision,
+                        vec![other.clone(), child.clone()],
+                        format!(
+                            "{} contains multiple child states which when converted to upper camel case are {}",
+                            describe(state),
+                            name
+                        ),
+                    ));
+                }
+            }
+        }
+
+        let mut edges: BTreeSet<(&StateId, String, &StateId)> = BTreeSet::new();
+        for transition in self.state_transition_out.values().flatten() {
+            let Some(name) = self.edge_name(transition) else {
+                continue;
+            };
+            let from = self.redirected_from(transition);
+            if !edges.insert((from, name.clone(), &transition.1)) {
+                diagnostics.push(Diagnostic::new(
+                    Rule::EdgeNameCollision,
+                    vec![from.clone()],
+                    format!(
+                        "multiple transitions are exiting from state {} which when converted to upper camel case are {}",
+                        from.display_name(),
+                        name
+                    ),
+                ));
+            }
+        }
+
+        let mut completions: BTreeMap<&StateId, &StateId> = BTreeMap::new();
+        for transition in self.state_transition_out.values().flatten() {
+            if transition.2.is_some() {
+                continue;
+            }
+            let from = self.redirected_from(transition);
+            if let Some(other) = completions.insert(from, &transition.1) {
+                diagnostics.push(Diagnostic::new(
+                    Rule::AmbiguousCompletion,
+                    vec![from.clone(), other.clone(), transition.1.clone()],
+                    format!(
+                        "state {} has multiple unlabelled transitions, to {} and {}; label one of them",
+                        from.display_name(),
+                        other.display_name(),
+                        transition.1.display_name()
+                    ),
+                ));
+            }
+        }
+
+        let root_start = StateId::root().start();
+        if self.state_parent.contains_key(&root_start) {
+            let reached = self.reach([&root_start], true);
+            for (state, parent) in &self.state_parent {
+                if !covers(&reached, state) && (parent.0.is_empty() || covers(&reached, parent)) {
+                    diagnostics.push(Diagnostic::new(
+                        Rule::Unreachable,
+                        vec![state.clone()],
+                        format!(
+                            "{} cannot be reached from the initial state",
+                            describe(state)
+                        ),
+                    ));
+                }
+            }
+            for state in self.state_children.keys() {
+                let exits =
+                    self.state_transition_out.contains_key(state) || self.exit(state) != state;
+                if !state.0.is_empty()
+                    && exits
+                    && covers(&reached, state)
+                    && !reached.contains(self.exit(state))
+                {
+                    diagnostics.push(Diagnostic::new(
+                        Rule::NeverExited,
+                        vec![state.clone()],
+                        format!("{} is entered but can never be exited", describe(state)),
+                    ));
+                }
+            }
+            for transition in self.state_transition_out.values().flatten() {
+                if covers(&reached, &transition.0)
+                    && !reached.contains(self.redirected_from(transition))
+                {
+                    diagnostics.push(Diagnostic::new(
+                        Rule::DeadTransition,
+                        vec![transition.0.clone(), transition.1.clone()],
+                        format!("transition {} can never fire", transition.display_name()),
+                    ));
+                }
+            }
+        }
+
+        let root_end = StateId::root().end();
+        if self.state_parent.contains_key(&root_end) {
+            let finishing = self.reach([&root_end], false);
+            for (state, parent) in &self.state_parent {
+                if !covers(&finishing, state) && (parent.0.is_empty() || covers(&finishing, parent))
+                {
+                    diagnostics.push(Diagnostic::new(
+                        Rule::CannotReachEnd,
+                        vec![state.clone()],
+                        format!("{} has no path to the final state", describe(state)),
+                    ));
+                }
+            }
+        }
+
+        let mut aliases: BTreeMap<&String, &StateId> = BTreeMap::new();
+        for (state, alias) in &self.state_alias {
+            if let Some(other) = aliases.insert(alias, state) {
+                diagnostics.push(Diagnostic::new(
+                    Rule::DuplicateAlias,
+                    vec![other.clone(), state.clone()],
+                    format!(
+                        "states {} and {} share the alias \"{}\"",
+                        other.display_name(),
+                        state.display_name(),
+                        alias
+                    ),
+                ));
+            }
+        }
+
+        diagnostics
+    }
+}
@@ -2,50 +2,29 @@
 
 extern crate proc_macro;
 use anyhow::bail;
-use heck::{ToSnakeCase, ToUpperCamelCase};
 use itertools::Either::{Left, Right};
 use proc_macro2::Span;
 use proc_macro2::TokenStream;
-use quote::quote;
+use quote::{format_ident, quote, quote_spanned};
 use std::cmp::{max, min};
-use std::collections::VecDeque;
 use std::collections::{BTreeMap, BTreeSet};
 use std::fs::File;
 use std::io::Read;
 use std::iter;
-use syn::LitStr;
-use syn::{parse::Parse, parse::ParseStream, parse_macro_input, Ident, Result};
-use zero_cost_state_machine_mermaid::{frames, TransitionId};
+use std::path::PathBuf;
+use syn::{parse_macro_input, parse_quote, Ident, Item, ItemMod, LitStr};
+use zero_cost_state_machine_mermaid::TransitionId;
+use zero_cost_state_machine_mermaid::{is_identifier, Diagnostic, Diagram, Rule, KEYWORDS};
 use zero_cost_state_machine_mermaid::{state_id, Frame, StateId};
-use zero_cost_state_machine_mermaid::{Diagram, Frames};
 
+mod options;
 #[cfg(test)]
 mod test;
 
-#[derive(Debug)]
-struct FileName {
-    filename: String,
-}
-
-impl Parse for FileName {
-    fn parse(input: ParseStream) -> Result<Self> {
-        let lit_file: syn::LitStr = input.parse()?;
-        Ok(Self {
-            filename: lit_file.value(),
-        })
-    }
-}
+use options::{Backend, CompletionEdges, Edges, LintLevel, MacroInput, Options};
 
-struct MacroInput {
-    contents: LitStr,
-}
-
-impl Parse for MacroInput {
-    fn parse(input: ParseStream) -> Result<Self> {
-        let contents: LitStr = input.parse()?;
-        Ok(MacroInput { contents })
-    }
-}
+/// source path, source depth, target path, target depth
+type RelativeName = (Vec<String>, usize, Vec<String>, usize);
 
 #[derive(Default, PartialEq, Debug)]
 struct Aux<'a> {
@@ -53,53 +32,20 @@ struct Aux<'a> {
     pub transition_from_end_redirection: BTreeMap<&'a TransitionId, &'a StateId>,
     pub child_node_canonical_name: BTreeMap<&'a StateId, String>,
     pub edge_canonical_name: BTreeMap<&'a TransitionId, Option<String>>,
-    pub relative_canonical_name:
-        BTreeMap<&'a TransitionId, (Vec<String>, usize, Vec<String>, usize)>,
+    pub relative_canonical_name: BTreeMap<&'a TransitionId, RelativeName>,
+    /// The states on a cycle, and whether the cycle is unbounded.
+    pub cyclic_states: BTreeMap<&'a StateId, bool>,
+    pub nondeterministic_transitions: BTreeSet<&'a TransitionId>,
 }
 
 impl<'a> Aux<'a> {
-    fn human_readable_name(state: &StateId) -> anyhow::Result<Option<String>> {
-        let parent_of_origin: anyhow::Result<String> = state
-            .0
-            .range(0..state.0.len().saturating_sub(1))
-            .map(|f| match f {
-                Frame::Start => bail!("the special state Start cannot have children"),
-                Frame::End => bail!("the special state End cannot have children"),
-                Frame::History => bail!("the special state History cannot have children"),
-                Frame::DeepHistory => bail!("the special state DeepHistory cannot have children"),
-                Frame::State { name } => Ok(format!("{}.", name)),
-            })
-            .collect();
-        let parent_of_origin = parent_of_origin?;
-        let origin = match state.0.iter().last() {
-            Some(Frame::Start) => anyhow::Ok("[*]".into()),
-            Some(Frame::End) => anyhow::Ok("[*]".into()),
-            Some(Frame::History) => anyhow::Ok("[H]".into()),
-            Some(Frame::DeepHistory) => anyhow::Ok("[H*]".into()),
-            Some(Frame::State { name }) => anyhow::Ok(name.clone()),
-            None => return Ok(None),
-        }?;
-        Ok(Some(format!("{}{}", parent_of_origin, origin)))
-    }
-    fn transition_from_end_redirection(
-        diagram: &Diagram,
-    ) -> anyhow::Result<BTreeMap<&TransitionId, &StateId>> {
+    fn transition_from_end_redirection(diagram: &Diagram) -> BTreeMap<&TransitionId, &StateId> {
         let mut transition_from_end_redirection: BTreeMap<&TransitionId, &StateId> =
             BTreeMap::new();
         for (state, children) in diagram.state_children.iter() {
-            let s = Self::human_readable_name(state)?;
-            let s = s
-                .map(|s| format!("state {}", s))
-                .unwrap_or(format!("the special state Root"));
-            let end_state = children.iter().find(|s| match s.0.iter().last() {
-                Some(Frame::End) => true,
-                _ => false,
-            });
-            if end_state.is_none() {
-                if diagram.state_transition_out.contains_key(state) {
-                    bail!("{} must contain an End state", s);
-                }
-            }
+            let end_state = children
+                .iter()
+                .find(|s| matches!(s.0.iter().last(), Some(Frame::End)));
             for transition in diagram
                 .state_transition_out
                 .get(state)
@@ -111,27 +57,15 @@ impl<'a> Aux<'a> {
                 }
             }
         }
-        Ok(transition_from_end_redirection)
+        transition_from_end_redirection
     }
-    fn transition_to_start_redirection(
-        diagram: &Diagram,
-    ) -> anyhow::Result<BTreeMap<&TransitionId, &StateId>> {
+    fn transition_to_start_redirection(diagram: &Diagram) -> BTreeMap<&TransitionId, &StateId> {
         let mut transition_to_start_redirection: BTreeMap<&TransitionId, &StateId> =
             BTreeMap::new();
         for (state, children) in diagram.state_children.iter() {
-            let s = Self::human_readable_name(state)?;
-            let s = s
-                .map(|s| format!("state {}", s))
-                .unwrap_or(format!("the special state Root"));
-            let start_state = children.iter().find(|s| match s.0.iter().last() {
-                Some(Frame::Start) => true,
-                _ => false,
-            });
-            if start_state.is_none() {
-                if diagram.state_transition_in.contains_key(state) {
-                    bail!("{} must contain Start state", s);
-                }
-            }
+            let start_state = children
+                .iter()
+                .find(|s| matches!(s.0.iter().last(), Some(Frame::Start)));
             for transition in diagram
                 .state_transition_in
                 .get(state)
@@ -143,77 +77,59 @@ impl<'a> Aux<'a> {
                 }
             }
         }
-        Ok(transition_to_start_redirection)
+        transition_to_start_redirection
     }
-    fn child_node_canonical_name(diagram: &Diagram) -> anyhow::Result<BTreeMap<&StateId, String>> {
+    fn child_node_canonical_name(diagram: &Diagram) -> BTreeMap<&StateId, String> {
         let mut child_node_canonical_name: BTreeMap<&StateId, String> = BTreeMap::new();
-        for (state, children) in diagram.state_children.iter() {
-            let s = Self::human_readable_name(state)?;
-            let s = s
-                .map(|s| format!("state {}", s))
-                .unwrap_or(format!("the special state Root"));
-            for child in children {
-                match child.0.iter().last() {
-                    Some(Frame::State { name }) => {
-                        let converted = name.to_upper_camel_case();
-                        if let Some(n) = child_node_canonical_name.get(child) {
-                            if n == &converted {
-                                bail!("{} contains multiple child states which when converted to upper camel case are {}", s, converted);
-                            }
-                        }
-                        child_node_canonical_name.insert(child, name.to_upper_camel_case());
-                    }
-                    Some(Frame::Start) => {
-                        child_node_canonical_name.insert(child, "Start".into());
-                    }
-                    Some(Frame::End) => {
-                        child_node_canonical_name.insert(child, "End".into());
-                    }
-                    Some(Frame::History) => {
-                        child_node_canonical_name.insert(child, "History".into());
-                    }
-                    Some(Frame::DeepHistory) => {
-                        child_node_canonical_name.insert(child, "DeepHistory".into());
-                    }
-                    None => {}
-                }
+        for child in diagram.state_children.values().flatten() {
+            if let Some(name) = diagram.node_name(child) {
+                child_node_canonical_name.insert(child, name);
             }
         }
-        Ok(child_node_canonical_name)
+        child_node_canonical_name
     }
     fn edge_canonical_name(
-        diagram: &Diagram,
-    ) -> anyhow::Result<BTreeMap<&TransitionId, Option<String>>> {
+        diagram: &'a Diagram,
+        options: &Options,
+    ) -> anyhow::Result<BTreeMap<&'a TransitionId, Option<String>>> {
+        let mut completions: BTreeMap<&StateId, usize> = BTreeMap::new();
+        for edge in diagram.state_transition_out.values().flatten() {
+            if edge.2.is_none() {
+                *completions
+                    .entry(diagram.redirected_from(edge))
+                    .or_default() += 1;
+            }
+        }
         let mut edge_canonical_name: BTreeMap<&TransitionId, Option<String>> = BTreeMap::new();
-        for (state, edges) in diagram.state_transition_out.iter() {
-            let s = Self::human_readable_name(state)?;
-            let s = if let Some(s) = s {
-                s
-            } else {
-                bail!("no transition can lead out of the special state Root");
-            };
-            for edge in edges {
-                let converted = edge.2.as_ref().map(|e| e.to_upper_camel_case());
-                match (converted, edge_canonical_name.get(edge)) {
-                    (Some(a), Some(Some(b))) => {
-                        if &a == b {
-                            bail!("multiple transitions are exiting from state {} which when converted to upper camel case are {}", s, a);
-                        }
-                    }
-                    (Some(a), Some(None)) => {
-                        edge_canonical_name.insert(edge, Some(a));
-                    }
-                    (Some(a), None) => {
-                        edge_canonical_name.insert(edge, Some(a));
-                    }
-                    (None, Some(Some(_))) => {
-                        edge_canonical_name.insert(edge, None);
-                    }
-                    (None, Some(None)) => {}
-                    (None, None) => {
-                        edge_canonical_name.insert(edge, None);
-                    }
+        let mut labelled: BTreeMap<(Vec<String>, String), &TransitionId> = BTreeMap::new();
+        for edge in diagram.state_transition_out.values().flatten() {
+            let name = match &edge.2 {
+                Some(_) => diagram.edge_name(edge),
+                None if options.completion_edges == CompletionEdges::Named
+                    && completions[diagram.redirected_from(edge)] > 1 =>
+                {
+                    Some(diagram.completion_edge_name(edge))
                 }
+                None => None,
+            };
+            if let (Some(_), Some(name)) = (&edge.2, &name) {
+                labelled.insert((edge_module(diagram, options, edge), name.clone()), edge);
+            }
+            edge_canonical_name.insert(edge, name);
+        }
+        for (edge, name) in &edge_canonical_name {
+            let Some(name) = name.as_ref().filter(|_| edge.2.is_none()) else {
+                continue;
+            };
+            if let Some(other) = labelled.get(&(edge_module(diagram, options, edge), name.clone()))
+            {
+                bail!(
+                    "the unlabelled transition `{}` is named `edge::{}`, as is the edge of `{}`; \
+                     rename one of them",
+                    edge.display_name(),
+                    name,
+                    other.display_name()
+                );
             }
         }
         Ok(edge_canonical_name)
@@ -222,131 +138,111 @@ impl<'a> Aux<'a> {
         diagram: &'a Diagram,
         transition_from_end_redirection: &BTreeMap<&TransitionId, &StateId>,
         transition_to_start_redirection: &BTreeMap<&TransitionId, &StateId>,
-    ) -> anyhow::Result<BTreeMap<&'a TransitionId, (Vec<String>, usize, Vec<String>, usize)>> {
-        let mut relative_canonical_name: BTreeMap<
-            &TransitionId,
-            (Vec<String>, usize, Vec<String>, usize),
-        > = BTreeMap::new();
+    ) -> BTreeMap<&'a TransitionId, RelativeName> {
+        let mut relative_canonical_name: BTreeMap<&TransitionId, RelativeName> = BTreeMap::new();
         for (_state, edges) in diagram.state_transition_out.iter() {
             for edge in edges {
                 let from_node = &edge.0;
                 let from_node = transition_from_end_redirection
                     .get(edge)
                     .unwrap_or(&from_node);
-                let from_node: Vec<_> = from_node.0.iter().collect();
                 let to_node = &edge.1;
                 let to_node = transition_to_start_redirection
                     .get(edge)
                     .unwrap_or(&to_node);
-                let to_node: Vec<_> = to_node.0.iter().collect();
+                let common_len = diagram.lowest_common_ancestor(from_node, to_node).0.len();
+                let (from_len, to_len) = (from_node.0.len(), to_node.0.len());
 
-                let mut i = from_node.len();
-                let mut j = to_node.len();
-                loop {
-                    if i == j && &from_node[..i] == &to_node[..j] {
-                        break;
-                    }
-                    if i < j {
-                        j -= 1;
-                    } else if j < i {
-                        i -= 1;
-                    } else {
-                        i -= 1;
-                        j -= 1;
-                    }
-                }
-                let common_len = i;
-                let origin_ascent = (from_node.len() - common_len).saturating_sub(1);
-                let target_descent = (to_node.len() - common_len).saturating_sub(1);
-                let relative_ascent = origin_ascent.saturating_sub(target_descent);
-
-                let source_frames = from_node
-                    .iter()
-                    .last()
+                let origin_ascent = (from_len - common_len).saturating_sub(1);
+
+                let source_frames = diagram
+                    .node_name(from_node)
                     .into_iter()
-                    .flat_map(|f| match f {
-                        Frame::Start => ["node".into(), "Start".into()],
-                        Frame::End => ["node".into(), "End".into()],
-                        Frame::History => ["node".into(), "History".into()],
-                        Frame::DeepHistory => ["node".into(), "DeepHistory".into()],
-                        Frame::State { name } => ["node".into(), name.to_upper_camel_case()],
-                    })
+                    .flat_map(|n| ["node".into(), n])
                     .collect();
 
-                // stay on the same level when we have no relative ascent
+                // climb out of the modules the source is nested in below the common ancestor
                 let supers = (0..origin_ascent).map(|_| "super".into());
 
                 // descend through modules when target ascent is greater than origin ascent
-                let modules = to_node[common_len..max(common_len, to_node.len().saturating_sub(1))]
-                    .into_iter()
-                    .filter_map(|f| match f {
-                        Frame::Start => None,
-                        Frame::End => None,
-                        Frame::History => None,
-                        Frame::DeepHistory => None,
-                        Frame::State { name } => Some(name.to_snake_case()),
+                let modules =
+                    (common_len..max(common_len, to_len.saturating_sub(1))).filter_map(|i| {
+                        diagram.module_name(&StateId(to_node.0.range(..=i).cloned().collect()))
                     });
 
                 // final addressing
-                let t = to_node.iter().last().into_iter().flat_map(|f| match f {
-                    Frame::Start => ["node".into(), "Start".into()],
-                    Frame::End => ["node".into(), "End".into()],
-                    Frame::History => ["node".into(), "History".into()],
-                    Frame::DeepHistory => ["node".into(), "DeepHistory".into()],
-                    Frame::State { name } => ["node".into(), name.to_upper_camel_case()],
-                });
+                let t = diagram
+                    .node_name(to_node)
+                    .into_iter()
+                    .flat_map(|n| ["node".into(), n]);
 
                 let target_frames = supers.chain(modules).chain(t).collect();
 
-                // dbg!(&edge);
-                // dbg!(&from_node);
-                // dbg!(&to_node);
-                // dbg!(i);
-                // dbg!(j);
-                // dbg!(common_len);
-                // dbg!(&origin_ascent);
-                // dbg!(&target_descent);
-                // dbg!(&relative_ascent);
-                // dbg!(&source_frames);
-                // dbg!(&target_frames);
-                // println!("----------------------------------------------------------");
-
-                relative_canonical_name.insert(
-                    edge,
-                    (source_frames, from_node.len(), target_frames, to_node.len()),
-                );
+                relative_canonical_name
+                    .insert(edge, (source_frames, from_len, target_frames, to_len));
             }
         }
-        Ok(relative_canonical_name)
+        relative_canonical_name
     }
-    fn new(diagram: &'a Diagram) -> anyhow::Result<Self> {
-        let child_node_canonical_name = Self::child_node_canonical_name(&diagram)?;
-        let edge_canonical_name = Self::edge_canonical_name(&diagram)?;
-        let transition_from_end_redirection = Self::transition_from_end_redirection(&diagram)?;
-        let transition_to_start_redirection = Self::transition_to_start_redirection(&diagram)?;
+    fn new(diagram: &'a Diagram, options: &Options) -> anyhow::Result<Self> {
+        let named_completions = options.completion_edges == CompletionEdges::Named;
+        if let Some(error) = diagram
+            .validate()
+            .into_iter()
+            .filter(|d| !(named_completions && d.rule == Rule::AmbiguousCompletion))
+            .find(Diagnostic::is_error)
+        {
+            bail!("{}", error);
+        }
+        let child_node_canonical_name = Self::child_node_canonical_name(diagram);
+        let edge_canonical_name = Self::edge_canonical_name(diagram, options)?;
+        let transition_from_end_redirection = Self::transition_from_end_redirection(diagram);
+        let transition_to_start_redirection = Self::transition_to_start_redirection(diagram);
         let relative_canonical_name = Self::relative_canonical_name(
-            &diagram,
+            diagram,
             &transition_from_end_redirection,
             &transition_to_start_redirection,
-        )?;
+        );
+        let mut cyclic_states: BTreeMap<&StateId, bool> = BTreeMap::new();
+        for cycle in diagram.cycles() {
+            let unbounded = diagram.is_unbounded(&cycle);
+            for state in cycle {
+                *cyclic_states.entry(state).or_default() |= unbounded;
+            }
+        }
+        let nondeterministic_transitions = diagram.nondeterministic_transitions();
         Ok(Aux {
             transition_to_start_redirection,
             transition_from_end_redirection,
             child_node_canonical_name,
             edge_canonical_name,
             relative_canonical_name,
+            cyclic_states,
+            nondeterministic_transitions,
         })
     }
 }
 
+/// An identifier for a generated name, raw if the name is a keyword.
+fn ident(name: &str) -> Ident {
+    if KEYWORDS.contains(&name) && is_identifier(name) {
+        Ident::new_raw(name, Span::call_site())
+    } else {
+        Ident::new(name, Span::call_site())
+    }
+}
+
 fn module(
     diagram: &Diagram,
     aux @ Aux {
         child_node_canonical_name,
         edge_canonical_name,
         relative_canonical_name,
+        cyclic_states,
+        nondeterministic_transitions,
         ..
     }: &Aux,
+    options: &Options,
     root: &StateId,
 ) -> TokenStream {
     let depth = root.0.len();
@@ -377,42 +273,143 @@ fn module(
                         .state_transition_out
                         .get(root)
                         .into_iter()
-                        .flat_map(|s| s.into_iter()),
+                        .flat_map(|s| s.iter()),
                 )
         })
         .collect();
 
-    let child_nodes: BTreeSet<_> = child_nodes
+    let state_info = child_nodes
+        .iter()
+        .filter_map(|s| Some((child_node_canonical_name.get(s)?, s)))
+        .map(|(name, s)| {
+            let node = ident(name);
+            let alias = match diagram.state_alias.get(s) {
+                Some(alias) => quote! { ::core::option::Option::Some(#alias) },
+                None => quote! { ::core::option::Option::None },
+            };
+            let path = s.0.iter().map(Frame::display_name);
+            let description = diagram.state_description.get(s).into_iter().flatten();
+            let is_start = matches!(s.0.back(), Some(Frame::Start));
+            let is_end = matches!(s.0.back(), Some(Frame::End));
+            quote! {
+                impl StateInfo for node::#node {
+                    const NAME: &'static str = #name;
+                    const ALIAS: ::core::option::Option<&'static str> = #alias;
+                    const PATH: &'static [&'static str] = &[#(#path),*];
+                    const DEPTH: usize = #depth;
+                    const IS_START: bool = #is_start;
+                    const IS_END: bool = #is_end;
+                    const DESCRIPTION: &'static [&'static str] = &[#(#description),*];
+                }
+            }
+        })
+        .collect::<Vec<_>>();
+
+    // A composite is never the head of a `State`, so only simple nodes get an `At` trait.
+    let at_node_names: BTreeSet<&String> = child_nodes
         .iter()
+        .filter(|s| !diagram.is_composite(s))
         .filter_map(|s| child_node_canonical_name.get(s))
         .collect();
+    let child_nodes: BTreeMap<_, _> = child_nodes
+        .iter()
+        .filter_map(|s| Some((child_node_canonical_name.get(s)?, cyclic_states.get(s))))
+        .collect();
+    let derives = &options.derives;
+    let derive = &(!derives.is_empty()).then(|| quote! { #[derive(#(#derives),*)] });
+    let vis = &options.visibility;
     let child_nodes = child_nodes
         .into_iter()
-        .map(|s| Ident::new(s, Span::call_site()))
-        .map(|s| {
+        .map(|(s, cyclic)| (ident(s), cyclic))
+        .map(|(s, cyclic)| {
+            let doc = cyclic.map(|unbounded| {
+                let doc = if *unbounded {
+                    "This state participates in an unbounded cycle; do not recurse."
+                } else {
+                    "This state participates in a bounded cycle, listed in `CYCLES`."
+                };
+                quote! {
+                    #[doc = "# Cycles"]
+                    #[doc = ""]
+                    #[doc = #doc]
+                }
+            });
             quote! {
-                pub struct #s;
+                #doc
+                #derive
+                #vis struct #s;
             }
         });
+    let child_edges: BTreeSet<&TransitionId> = if options.edges == Edges::Shared && depth == 0 {
+        edge_canonical_name.keys().copied().collect()
+    } else {
+        child_edges
+    };
+    let mut edge_transitions: BTreeMap<&String, Vec<&TransitionId>> = BTreeMap::new();
+    for t in &child_edges {
+        if let Some(Some(name)) = edge_canonical_name.get(t) {
+            edge_transitions.entry(name).or_default().push(t);
+        }
+    }
+    let edge_info = (options.edges == Edges::Nested || depth == 0)
+        .then_some(&edge_transitions)
+        .into_iter()
+        .flatten()
+        .map(|(name, transitions)| {
+            let edge = ident(name);
+            let label = transitions
+                .iter()
+                .find_map(|t| t.2.as_ref())
+                .cloned()
+                .unwrap_or_default();
+            let mut notes: Vec<&String> = vec![];
+            for note in transitions
+                .iter()
+                .flat_map(|t| diagram.transition_note.get(t))
+                .flatten()
+            {
+                if !notes.contains(&note) {
+                    notes.push(note);
+                }
+            }
+            quote! {
+                impl EdgeInfo for edge::#edge {
+                    const LABEL: &'static str = #label;
+                    const NOTE: &'static [&'static str] = &[#(#notes),*];
+                }
+            }
+        })
+        .collect::<Vec<_>>();
     let child_edges: BTreeSet<_> = child_edges
         .iter()
         .filter_map(|t| edge_canonical_name.get(t))
         .collect();
-    let child_edges = child_edges
-        .into_iter()
-        .flat_map(|s| s.as_ref().map(|s| Ident::new(s, Span::call_site())))
-        .map(|s| {
+    let root_edges = &iter::repeat_n(quote! {super}, depth + 1).collect::<Vec<_>>();
+    let child_edges = child_edges.into_iter().flatten().map(|name| {
+        let s = ident(name);
+        if options.edges == Edges::Shared && depth > 0 {
             quote! {
-                pub struct #s;
+                #vis use #(#root_edges::)*edge::#s;
             }
-        });
+        } else if let Some(payload) = options.payloads.get(name) {
+            quote! {
+                #derive
+                #vis struct #s(pub #payload);
+            }
+        } else {
+            quote! {
+                #derive
+                #vis struct #s;
+            }
+        }
+    });
     let nodemod = quote! {
-        pub mod node {
+        #vis mod node {
             #(#child_nodes)*
         }
     };
     let edgemod = quote! {
-        pub mod edge {
+        #vis mod edge {
             #(#child_edges)*
         }
     };
@@ -451,13 +448,114 @@ fn module(
         .take(depth * 2)
         .chain(iter::once(v.clone()));
 
+    let title = diagram
+        .title
+        .as_ref()
+        .filter(|_| depth == 0)
+        .map(|title| quote! { #[doc = #title] });
     let state_struct = quote! {
-        pub struct State<#(#state_struct_node_edge_type_params),*> {
+        #title
+        #derive
+        #vis struct State<#(#state_struct_node_edge_type_params),*> {
             #(#node_paths)*
             pub head: S
         }
     };
 
+    let breadcrumbs = (depth > 0).then(|| {
+        let field = |name: &str, i: usize| format_ident!("{}{}", name, i);
+        let (mut ancestry, mut crumbs) = (quote! { () }, quote! { () });
+        let mut parent = ancestry.clone();
+        for i in 0..depth {
+            let (n, e) = (field("N", i), field("E", i));
+            let (node, edge) = (field("node", i), field("edge", i));
+            parent = ancestry.clone();
+            ancestry = quote! { Crumb<'a, #n, #e, #ancestry> };
+            crumbs = quote! { Crumb { from: &self.#node, edge: &self.#edge, parent: #crumbs } };
+        }
+        let (n, e) = (field("N", depth - 1), field("E", depth - 1));
+        let (node, edge) = (field("node", depth - 1), field("edge", depth - 1));
+        let params = node_edge_type_params.clone().take(depth * 2);
+        let state_params = node_edge_type_params.clone().take(depth * 2);
+        quote! {
+            impl<#(#params,)* S> Breadcrumbs for State<#(#state_params,)* S> {
+                type From = #n;
+                type Edge = #e;
+                type Ancestry<'a> = #ancestry where Self: 'a;
+                type Parent<'a> = #parent where Self: 'a;
+
+                fn entered_from(&self) -> &#n {
+                    &self.#node
+                }
+                fn entry_edge(&self) -> &#e {
+                    &self.#edge
+                }
+                fn ancestry(&self) -> Self::Ancestry<'_> {
+                    #crumbs
+                }
+                fn parent(&self) -> Self::Parent<'_> {
+                    self.ancestry().parent
+                }
+            }
+        }
+    });
+
+    let in_traits = diagram
+        .state_children
+        .get(root)
+        .into_iter()
+        .flatten()
+        .filter(|s| diagram.is_composite(s))
+        .filter_map(|s| diagram.node_name(s))
+        .map(|name| {
+            let trait_name = format_ident!("In{}", name.trim_start_matches('_'));
+            let doc = format!(
+                "Implemented by every `State` inside `{}`, at any depth.",
+                name
+            );
+            quote! {
+                #[doc = #doc]
+                #vis trait #trait_name {}
+            }
+        });
+    let in_impls = (1..=depth).filter_map(|i| {
+        let composite = StateId(root.0.range(..i).cloned().collect());
+        let name = diagram.node_name(&composite)?;
+        let trait_name = format_ident!("In{}", name.trim_start_matches('_'));
+        let supers = iter::repeat_n(quote! {super}, depth - i + 1);
+        let params = node_edge_type_params.clone().take(depth * 2);
+        let state_params = node_edge_type_params.clone().take(depth * 2);
+        Some(quote! {
+            impl<#(#params,)* S> #(#supers::)*#trait_name for State<#(#state_params,)* S> {}
+        })
+    });
+
+    let to_root = &iter::repeat_n(quote! {super}, depth).collect::<Vec<_>>();
+    let can_reach = diagram
+        .state_children
+        .get(root)
+        .into_iter()
+        .flatten()
+        .filter(|s| !diagram.is_composite(s))
+        .filter_map(|s| Some((s, diagram.node_name(s)?)))
+        .flat_map(|(s, name)| {
+            let node = ident(&name);
+            let node_edge_type_params = node_edge_type_params.clone();
+            let reached: BTreeSet<StateId> = diagram
+                .reachable_from(s)
+                .into_iter()
+                .flat_map(|r| (1..=r.0.len()).map(|i| StateId(r.0.range(..i).cloned().collect())))
+                .collect();
+            reached.into_iter().map(move |target| {
+                let target = diagram.type_path(&target).into_iter().map(|s| ident(&s));
+                let params = node_edge_type_params.clone().take(depth * 2);
+                let state_params = node_edge_type_params.clone().take(depth * 2);
+                quote! {
+                    impl<#(#params),*> CanReach<#(#to_root::)*#(#target)::*> for State<#(#state_params,)* node::#node> {}
+                }
+            })
+        });
+
     let child_transitions = diagram
         .state_children
         .get(root)
@@ -472,19 +570,69 @@ fn module(
                 .flat_map(|s| s.iter())
         })
         .filter(|t| {
-            edge_canonical_name.contains_key(t) &&
-                child_node_canonical_name.contains_key(&t.0) &&
-                relative_canonical_name.contains_key(t)
+            edge_canonical_name.contains_key(t)
+                && child_node_canonical_name.contains_key(&t.0)
+                && relative_canonical_name.contains_key(t)
         })
         .chain(
-            diagram.state_transition_out.get(root)
+            diagram
+                .state_transition_out
+                .get(root)
                 .into_iter()
-                .flat_map(|t| t.into_iter())
+                .flat_map(|t| t.iter()),
         )
+        .collect::<Vec<_>>();
+
+    // A guarded transition is told apart by the `node` type of its target as drawn, which is
+    // the composite itself rather than the `Start` it is entered through.
+    let try_target = |t: &TransitionId| {
+        let target = diagram.type_path(&t.1).into_iter().map(|s| ident(&s));
+        quote! { #(#to_root::)*#(#target)::* }
+    };
+    let mut exits_by_node: BTreeMap<&String, Vec<TokenStream>> = BTreeMap::new();
+    for t in &child_transitions {
+        let Some(from) = relative_canonical_name[t].0.last() else {
+            continue;
+        };
+        let transition = match &edge_canonical_name[t] {
+            Some(s) => {
+                let s = ident(s);
+                quote! { edge::#s }
+            }
+            None => quote! { () },
+        };
+        let bound = if nondeterministic_transitions.contains(t) {
+            let to = try_target(t);
+            quote! { TryPath<#to, #transition> }
+        } else {
+            quote! { Path<#transition> }
+        };
+        exits_by_node.entry(from).or_default().push(bound);
+    }
+    let at_traits = at_node_names.iter().map(|name| {
+        let at = format_ident!("At{}", name.trim_start_matches('_'));
+        let node = ident(name);
+        let params = node_edge_type_params.clone().take(depth * 2);
+        let state_params = node_edge_type_params.clone().take(depth * 2);
+        let bounds = exits_by_node.get(name).into_iter().flatten();
+        let doc = format!(
+            "Implemented by `State` at `node::{}` whatever the enclosing breadcrumbs, with the \
+            transitions out of it as supertraits.",
+            name
+        );
+        quote! {
+            #[doc = #doc]
+            #vis trait #at: Sized #(+ #bounds)* {}
+            impl<#(#params),*> #at for State<#(#state_params,)* node::#node> {}
+        }
+    });
+
+    let child_transitions = child_transitions
+        .into_iter()
         .map(|t| {
             let node_edge_type_params2 = node_edge_type_params.clone();
             let transition = &if let Some(s) = &edge_canonical_name[t] {
-                let transition = Ident::new(s, Span::call_site());
+                let transition = ident(s);
                 quote! {
                     edge::#transition
                 }
@@ -494,12 +642,12 @@ fn module(
             let state = &String::from("State");
             let (from_node, origin_depth, to_node, target_depth) = &relative_canonical_name[t];
             let ascent_to_target = to_node.iter().take_while(|s| s.as_str() != "node").chain(iter::once(state));
-            let ascent_to_target = ascent_to_target.map(|s| Ident::new(s, Span::call_site()));
+            let ascent_to_target = ascent_to_target.map(|s| ident(s));
             let ascent_to_target = quote! { #(#ascent_to_target)::* };
-            
-            let from_node = from_node.iter().map(|s| Ident::new(s, Span::call_site()));
+
+            let from_node = from_node.iter().map(|s| ident(s));
             let from_node = &quote! { #(#from_node)::* };
-            let to_node = to_node.iter().map(|s| Ident::new(s, Span::call_site()));
+            let to_node = to_node.iter().map(|s| ident(s));
             let to_node = quote! { #(#to_node)::* };
 
             let nfn = &quote! {#from_node};
@@ -508,9 +656,9 @@ fn module(
                 .chain(
                     iter::once(nfn.clone())
                 );
-                
+
             let tn = &quote! {#to_node};
-            
+
             let state_destination_node_edge_type_params = node_edge_type_params2
                 .clone()
                 .take(min(target_depth.saturating_sub(1), depth)*2)
@@ -530,11 +678,11 @@ fn module(
                 .chain(
                     iter::once(tn.clone())
                 );
-                
+
             let targ = &quote! {
                  #ascent_to_target<#(#state_destination_node_edge_type_params),*>
             };
-            
+
             let bindings = {
                 if target_depth > origin_depth {
                     Left(
@@ -597,9 +745,26 @@ fn module(
                     head: #to_node
                 }
             }));
-            
+
             let node_edge_type_params2 = node_edge_type_params2.take(depth*2);
 
+            if nondeterministic_transitions.contains(t) {
+                let to = try_target(t);
+                return quote! {
+                    impl<#(#node_edge_type_params2),*> TryPath<#to, #transition> for State<#(#state_origin_node_edge_type_params),*> {
+                        type Target = #targ;
+                        fn try_transition(self, path: #transition, _to: #to, guard: bool) -> ::core::result::Result<Self::Target, Self> {
+                            if !guard {
+                                return ::core::result::Result::Err(self);
+                            }
+                            ::core::result::Result::Ok(Self::Target {
+                                #(#bindings)*
+                            })
+                        }
+                    }
+                };
+            }
+
             quote! {
                 impl<#(#node_edge_type_params2),*> Path<#transition> for State<#(#state_origin_node_edge_type_params),*> {
                     type Target = #targ;
@@ -618,11 +783,11 @@ fn module(
         .flat_map(|s| s.iter())
         .filter(|s| diagram.state_children.contains_key(*s))
         .map(|s| {
-            let m = module(diagram, aux, s);
-            if let Some(Frame::State { name }) = s.0.iter().last() {
-                let name = Ident::new(&*name.to_snake_case(), Span::call_site());
+            let m = module(diagram, aux, options, s);
+            if let Some(name) = diagram.module_name(s) {
+                let name = ident(&name);
                 quote! {
-                    pub mod #name {
+                    #vis mod #name {
                         #m
                     }
                 }
@@ -630,77 +795,681 @@ fn module(
                 quote! {}
             }
         });
+    let dense_id = (options.backend == Backend::Table).then(|| {
+        let params = node_edge_type_params.clone().take(depth * 2);
+        let state_params = node_edge_type_params.clone().take(depth * 2);
+        quote! {
+            impl<#(#params,)* S: #(#to_root::)*dense::Id> State<#(#state_params,)* S> {
+                /// The dense id of the current state.
+                #vis const fn id(&self) -> #(#to_root::)*dense::StateId {
+                    S::ID
+                }
+            }
+        }
+    });
+
     quote! {
         use zero_cost_state_machine::*;
         #nodemod
         #edgemod
+        #(#state_info)*
+        #(#edge_info)*
         #state_struct
+        #breadcrumbs
+        #dense_id
+        #(#in_traits)*
+        #(#in_impls)*
+        #(#at_traits)*
+        #(#can_reach)*
         #(#child_transitions)*
         #(#mods)*
     }
 }
 
-#[proc_macro]
-pub fn statemachine_from_mermaid(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
-    let MacroInput { contents } = parse_macro_input!(input as MacroInput);
-    let contents = contents.value();
+/// Reports the warnings of [`Diagram::validate`] at the level chosen in `options`.
+///
+/// Proc macros cannot emit warnings on stable, so a warning is the use of a
+/// deprecated constant whose note is the diagnostic.
+fn lints(diagram: &Diagram, options: &Options, span: Span) -> TokenStream {
+    let warnings = diagram
+        .validate()
+        .into_iter()
+        .filter(|d| !d.is_error())
+        .map(|d| d.to_string());
+    match options.lints {
+        LintLevel::Allow => quote! {},
+        LintLevel::Warn => quote! {
+            #(
+                const _: () = {
+                    #[deprecated(note = #warnings)]
+                    const ZCSM_LINT: () = ();
+                    ZCSM_LINT
+                };
+            )*
+        },
+        LintLevel::Deny => quote_spanned! {span=>
+            #(compile_error!(#warnings);)*
+        },
+    }
+}
+
+/// `CYCLES` lists the `node` types of each cycle, as paths relative to the machine root, and
+/// whether it is bounded. Cycles only through `[*]` pseudo-states are left out.
+fn cycles(diagram: &Diagram, options: &Options) -> TokenStream {
+    let vis = &options.visibility;
+    let cycles = diagram.cycles().into_iter().filter_map(|cycle| {
+        let bounded = !diagram.is_unbounded(&cycle);
+        let states: Vec<_> = cycle
+            .into_iter()
+            .filter(|s| matches!(s.0.back(), Some(Frame::State { .. })))
+            .map(|s| diagram.type_path(s).join("::"))
+            .collect();
+        (!states.is_empty()).then(|| {
+            quote! {
+                zero_cost_state_machine::table::CycleInfo { states: &[#(#states),*], bounded: #bounded }
+            }
+        })
+    });
+    quote! {
+        /// The cycles of the machine: sets of states it can move between any number of times.
+        #vis const CYCLES: &[zero_cost_state_machine::table::CycleInfo] = &[#(#cycles),*];
+    }
+}
+
+/// Generates the items of the machine for `contents`, or the tokens reporting why not.
+fn generate(
+    input: &MacroInput,
+    contents: &str,
+) -> std::result::Result<(Diagram, Options, TokenStream), TokenStream> {
+    let span = input.contents.span();
+    let error = |e: &dyn std::fmt::Display| {
+        let error_message = e.to_string();
+        quote_spanned! {span=>
+            compile_error!(#error_message);
+        }
+    };
 
-    let (_, diagram) = match zero_cost_state_machine_mermaid::human_readable_error(
+    let (_, diagram) = zero_cost_state_machine_mermaid::human_readable_error(
         zero_cost_state_machine_mermaid::mermaid,
-    )(&*contents)
-    {
-        Ok((input, diagram)) => (input, diagram),
-        Err(e) => {
-            let error_message = e.to_string();
-            return quote! {
-                compile_error!(#error_message);
+    )(contents)
+    .map_err(|e| error(&e))?;
+
+    let options = input.options(&diagram).map_err(|e| e.to_compile_error())?;
+
+    let aux = &Aux::new(&diagram, &options).map_err(|e| error(&e))?;
+
+    let lints = lints(&diagram, &options, span);
+    let cycles = cycles(&diagram, &options);
+    let table = table(&diagram, aux, &options);
+    let zero_sized = zero_sized(&diagram, aux, &options);
+    let dense = match options.backend {
+        Backend::Typestate => quote! {},
+        Backend::Table => dense(&diagram, aux, &options).map_err(|e| error(&e))?,
+    };
+    let vis = &options.visibility;
+    let module = module(&diagram, aux, &options, &state_id![]);
+    let machine = quote! {
+        #lints
+        /// The diagram the machine was generated from, for
+        /// [`Mermaid`](zero_cost_state_machine::Mermaid).
+        #vis const DIAGRAM: &str = #contents;
+        #cycles
+        #table
+        #zero_sized
+        #dense
+        #module
+    };
+    Ok((diagram, options, machine))
+}
+
+/// Generates the machine for `contents` inside a module, named by `module = name` or else
+/// after the title. Without either, the machine is generated in place unless `in_module`
+/// requires a module.
+fn expand(input: &MacroInput, contents: &str, in_module: bool) -> TokenStream {
+    let span = input.contents.span();
+    let (diagram, options, machine) = match generate(input, contents) {
+        Ok(generated) => generated,
+        Err(e) => return e,
+    };
+    let name = match (&options.module, diagram.title_module_name()) {
+        (Some(name), _) => name.clone(),
+        (None, Some(name)) if is_identifier(&name) => ident(&name),
+        (None, Some(_)) => {
+            let error = format!(
+                "the title {:?} does not make a module name; pass `module = name`",
+                diagram.title.as_deref().unwrap_or_default()
+            );
+            return quote_spanned! {span=> compile_error!(#error); };
+        }
+        (None, None) if in_module => {
+            return quote_spanned! {span=>
+                compile_error!("the diagram has no title to name its module after; pass `module = name`");
             }
-            .into();
         }
+        (None, None) => return machine,
     };
+    let heading = diagram.title.iter().map(|title| format!("# {}", title));
+    let vis = &options.visibility;
+    quote! {
+        #(#[doc = #heading])*
+        #vis mod #name {
+            #machine
+        }
+    }
+}
 
-    let aux = &match Aux::new(&diagram) {
-        Ok(a) => a,
+/// Reads the diagram file named by `path`, relative to the root of the crate being built.
+fn read_diagram(path: &LitStr) -> std::result::Result<(String, String), TokenStream> {
+    let root = std::env::var_os("CARGO_MANIFEST_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_default();
+    let file_path = root.join(path.value());
+    let file_path_str = file_path.display().to_string();
+    let mut contents = String::new();
+    match File::open(&file_path).and_then(|mut f| f.read_to_string(&mut contents)) {
+        Ok(_) => Ok((file_path_str, contents)),
         Err(e) => {
-            let error_message = e.to_string();
-            return quote! {
+            let error_message = format!("cannot read {}: {}", file_path_str, e);
+            Err(quote_spanned! {path.span()=>
                 compile_error!(#error_message);
+            })
+        }
+    }
+}
+
+/// Moves the generated `items` into `module`, merging generated modules into the user's
+/// modules of the same name so handwritten items sit beside the generated ones.
+fn merge_items(module: &mut Vec<Item>, items: Vec<Item>) {
+    for item in items {
+        if let Item::Mod(generated) = item {
+            let existing = module.iter_mut().find_map(|i| match i {
+                Item::Mod(m) if m.ident == generated.ident && m.content.is_some() => Some(m),
+                _ => None,
+            });
+            match existing {
+                Some(existing) => {
+                    let content = &mut existing.content.as_mut().unwrap().1;
+                    merge_items(
+                        content,
+                        generated.content.map(|(_, c)| c).unwrap_or_default(),
+                    );
+                }
+                None => module.push(Item::Mod(generated)),
             }
-            .into();
+        } else {
+            module.push(item);
         }
+    }
+}
+
+/// Asserts that the `node` and `edge` types, and each `State` the transitions from a
+/// root-level state can lead to, are zero-sized. Edges with a payload are left out, and so are
+/// states whose breadcrumbs store one.
+fn zero_sized(diagram: &Diagram, aux: &Aux, options: &Options) -> TokenStream {
+    let tokens = |path: &[String]| {
+        let path = path.iter().map(|s| ident(s));
+        quote! { #(#path)::* }
+    };
+    let carries_payload = |path: &Option<Vec<String>>| {
+        path.as_ref()
+            .and_then(|p| p.last())
+            .is_some_and(|name| options.payloads.contains_key(name))
     };
 
-    return module(&diagram, aux, &state_id![]).into();
+    let nodes = aux
+        .child_node_canonical_name
+        .keys()
+        .map(|s| tokens(&diagram.type_path(s)));
+    let edges: BTreeSet<Vec<String>> = diagram
+        .state_transition_out
+        .values()
+        .flatten()
+        .map(|t| edge_path(diagram, aux, options, t))
+        .filter(|path| !carries_payload(path))
+        .flatten()
+        .collect();
+    let edges = edges.iter().map(|path| tokens(path));
+
+    // The breadcrumbs of a `State` are the `node` left and the `edge` taken to enter each
+    // enclosing composite, as the `Path` impls of `module` build them, and whether that edge
+    // carries a payload.
+    type Crumbs = Vec<(TokenStream, TokenStream, bool)>;
+    let mut seen: BTreeSet<(&StateId, String)> = BTreeSet::new();
+    let mut states: Vec<(&StateId, Crumbs)> = diagram
+        .state_children
+        .get(&state_id![])
+        .into_iter()
+        .flatten()
+        .filter(|s| !diagram.is_composite(s))
+        .map(|s| (s, vec![]))
+        .collect();
+    let mut instantiations = vec![];
+    while let Some((state, crumbs)) = states.pop() {
+        let crumb_types: Vec<_> = crumbs.iter().flat_map(|(n, e, _)| [n, e]).collect();
+        if !seen.insert((state, quote! { #(#crumb_types)* }.to_string())) {
+            continue;
+        }
+        if crumbs.iter().all(|(_, _, payload)| !payload) {
+            let module = diagram.module_path(state);
+            let module = module.iter().map(|s| ident(s));
+            let head = tokens(&diagram.type_path(state));
+            instantiations.push(quote! { #(#module::)*State<#(#crumb_types,)* #head> });
+        }
+
+        let out = diagram.state_transition_out.values().flatten();
+        for t in out.filter(|t| diagram.redirected_from(t) == state) {
+            let edge = edge_path(diagram, aux, options, t);
+            let to = diagram.redirected_to(t);
+            let (from_len, to_len) = (state.0.len(), to.0.len());
+            let mut next: Crumbs = crumbs
+                .iter()
+                .take(min(to_len, from_len) - 1)
+                .cloned()
+                .collect();
+            if to_len > from_len {
+                let payload = carries_payload(&edge);
+                let edge = match &edge {
+                    Some(path) => tokens(path),
+                    None => quote! { () },
+                };
+                next.push((tokens(&diagram.type_path(state)), edge, payload));
+                next.extend(iter::repeat_n(
+                    (
+                        quote! { zero_cost_state_machine::NoNode },
+                        quote! { zero_cost_state_machine::NoEdge },
+                        false,
+                    ),
+                    to_len - from_len - 1,
+                ));
+            }
+            states.push((to, next));
+        }
+    }
+
+    quote! {
+        #(const _: () = ::core::assert!(::core::mem::size_of::<#nodes>() == 0);)*
+        #(const _: () = ::core::assert!(::core::mem::size_of::<#edges>() == 0);)*
+        #(const _: () = ::core::assert!(::core::mem::size_of::<#instantiations>() == 0);)*
+    }
+}
+
+/// Path of the `edge` type of a labelled or named transition, relative to the machine root.
+fn edge_path(
+    diagram: &Diagram,
+    aux: &Aux,
+    options: &Options,
+    t: &TransitionId,
+) -> Option<Vec<String>> {
+    let name = aux.edge_canonical_name.get(t)?.as_ref()?;
+    let mut path = edge_module(diagram, options, t);
+    path.extend(["edge".to_string(), name.clone()]);
+    Some(path)
 }
 
+/// Path of the module whose `edge` module defines the type of a transition, relative to the
+/// machine root.
+fn edge_module(diagram: &Diagram, options: &Options, t: &TransitionId) -> Vec<String> {
+    match options.edges {
+        Edges::Shared => vec![],
+        Edges::Nested => {
+            let mut path = diagram.module_path(&t.0);
+            if diagram.is_composite(&t.0) {
+                path.extend(diagram.module_name(&t.0));
+            }
+            path
+        }
+    }
+}
+
+/// `STATES` and `TRANSITIONS` describe the flattened machine, as its `Path` impls do.
+fn table(diagram: &Diagram, aux: &Aux, options: &Options) -> TokenStream {
+    let vis = &options.visibility;
+    let path = |s: &StateId| diagram.type_path(s).join("::");
+    let states = diagram.flat_states().into_iter().map(|s| {
+        let node = path(s);
+        let names = s.0.iter().map(Frame::display_name);
+        let alias = match diagram.state_alias.get(s) {
+            Some(alias) => quote! { ::core::option::Option::Some(#alias) },
+            None => quote! { ::core::option::Option::None },
+        };
+        let depth = s.0.len().saturating_sub(1);
+        let is_start = matches!(s.0.back(), Some(Frame::Start));
+        let is_end = matches!(s.0.back(), Some(Frame::End));
+        let description = diagram.state_description.get(s).into_iter().flatten();
+        quote! {
+            table::StateInfo {
+                node: #node,
+                path: &[#(#names),*],
+                alias: #alias,
+                depth: #depth,
+                is_start: #is_start,
+                is_end: #is_end,
+                description: &[#(#description),*],
+            }
+        }
+    });
+    let transitions = diagram.state_transition_out.values().flatten().map(|t| {
+        let from = path(
+            aux.transition_from_end_redirection
+                .get(t)
+                .copied()
+                .unwrap_or(&t.0),
+        );
+        let to = path(
+            aux.transition_to_start_redirection
+                .get(t)
+                .copied()
+                .unwrap_or(&t.1),
+        );
+        let label = match &t.2 {
+            Some(label) => quote! { ::core::option::Option::Some(#label) },
+            None => quote! { ::core::option::Option::None },
+        };
+        let edge = match edge_path(diagram, aux, options, t) {
+            Some(path) => path.join("::"),
+            None => "()".into(),
+        };
+        let guarded = aux.nondeterministic_transitions.contains(t);
+        quote! {
+            table::TransitionInfo {
+                from: #from,
+                to: #to,
+                label: #label,
+                edge: #edge,
+                guarded: #guarded,
+            }
+        }
+    });
+    quote! {
+        /// The states of the flattened machine, sorted by path with the `Start` and `End` of each level first.
+        #vis const STATES: &[zero_cost_state_machine::table::StateInfo] = {
+            use zero_cost_state_machine::table;
+            &[#(#states),*]
+        };
+        /// The transitions of the flattened machine, sorted by source, target and label.
+        #vis const TRANSITIONS: &[zero_cost_state_machine::table::TransitionInfo] = {
+            use zero_cost_state_machine::table;
+            &[#(#transitions),*]
+        };
+    }
+}
+
+#[proc_macro]
+pub fn statemachine_from_mermaid(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as MacroInput);
+    expand(&input, &input.contents.value(), false).into()
+}
+
+/// Like `statemachine_from_mermaid!`, but reads the diagram from a file, relative to the
+/// crate root, and generates the machine in a module named after its title unless
+/// `module = name` is given.
 #[proc_macro]
 pub fn statemachine_from_mermaid_file(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
-    let input = parse_macro_input!(input as FileName);
-    let cwd = std::env::current_dir().unwrap();
-    let file_path = cwd.join(&input.filename);
-    let file_path_str = file_path.display().to_string();
-    let mut file = File::open(file_path).unwrap();
-    let mut contents = String::new();
-    file.read_to_string(&mut contents).unwrap();
+    let input = parse_macro_input!(input as MacroInput);
+    let (file_path, contents) = match read_diagram(&input.contents) {
+        Ok(read) => read,
+        Err(e) => return e.into(),
+    };
+    let machine = expand(&input, &contents, true);
+    quote! {
+        const _: &str = include_str!(#file_path);
+        #machine
+    }
+    .into()
+}
 
-    let (_, _) = match zero_cost_state_machine_mermaid::human_readable_error(
-        zero_cost_state_machine_mermaid::mermaid,
-    )(&*contents)
+/// Generates the machine of a diagram file into the module it is attached to:
+///
+/// ```rust,ignore
+/// #[zero_cost_state_machine_macro::machine("flow.mmd", lints = deny)]
+/// mod flow {
+///     impl<N0, E0> state3::State<N0, E0, state3::node::Long1> {
+///         fn progress(&self) -> u8 { 50 }
+///     }
+/// }
+/// ```
+///
+/// The generated `node`, `edge` and `State` items and composite modules are added to the
+/// module; a handwritten module with the name of a generated one receives its items.
+#[proc_macro_attribute]
+pub fn machine(
+    attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(attr as MacroInput);
+    let mut module = parse_macro_input!(item as ItemMod);
+    let Some((_, items)) = &mut module.content else {
+        return syn::Error::new_spanned(
+            &module,
+            "expected a module with a body, `mod name { ... }`",
+        )
+        .to_compile_error()
+        .into();
+    };
+    let (file_path, contents) = match read_diagram(&input.contents) {
+        Ok(read) => read,
+        Err(e) => return quote! { #e #module }.into(),
+    };
+    let (diagram, machine) = match generate(&input, &contents) {
+        Ok((diagram, _, machine)) => (diagram, machine),
+        Err(e) => return quote! { #e #module }.into(),
+    };
+    let generated = match syn::parse2::<syn::File>(machine) {
+        Ok(file) => file.items,
+        Err(e) => return e.to_compile_error().into(),
+    };
+    items.push(parse_quote! { const _: &str = include_str!(#file_path); });
+    merge_items(items, generated);
+    let heading = diagram.title.iter().map(|title| format!("# {}", title));
+    quote! {
+        #(#[doc = #heading])*
+        #module
+    }
+    .into()
+}
+
+/// The `dense` module of the table backend: `repr` ids for the states of the flattened machine
+/// and for the edges, a `const` table of the transitions between them, and the traits tying
+/// the typestate types to their ids.
+fn dense(diagram: &Diagram, aux: &Aux, options: &Options) -> anyhow::Result<TokenStream> {
+    let vis = &options.visibility;
+    if diagram
+        .state_children
+        .get(&state_id![])
+        .into_iter()
+        .flatten()
+        .any(|s| diagram.is_composite(s) && diagram.module_name(s).as_deref() == Some("dense"))
     {
-        Ok((input, diagram)) => (input, diagram),
-        Err(e) => {
-            let error_message = e.to_string();
-            return quote! {
-                compile_error!(#error_message);
-            }
-            .into();
+        bail!("the table backend generates a `dense` module, which a composite state is named as");
+    }
+    let repr = |count: usize| match count {
+        0..=256 => quote! { u8 },
+        _ => quote! { u16 },
+    };
+
+    // Variants join the names of the enclosing composites to that of the state or edge.
+    let variant = |state: &StateId, name: Option<&String>| {
+        let names = (1..=state.0.len())
+            .filter_map(|i| diagram.node_name(&StateId(state.0.range(..i).cloned().collect())))
+            .chain(name.cloned());
+        let mut variant = String::new();
+        for name in names {
+            variant.push_str(if variant.is_empty() {
+                &name
+            } else {
+                name.trim_start_matches('_')
+            });
         }
+        variant
     };
+    let states: Vec<&StateId> = diagram.flat_states().into_iter().collect();
+    let variants: Vec<String> = states.iter().map(|s| variant(s, None)).collect();
+    if let Some(variant) = variants
+        .iter()
+        .find(|v| variants.iter().filter(|w| w == v).count() > 1)
+    {
+        bail!("the table backend names two states `StateId::{}`", variant);
+    }
+    let state_ids: BTreeMap<&StateId, usize> =
+        states.iter().enumerate().map(|(i, s)| (*s, i)).collect();
+
+    // Every edge type is its own event, so that equal labels in different composites differ.
+    let mut edges: BTreeMap<Option<Vec<String>>, String> =
+        BTreeMap::from([(None, "Completion".to_string())]);
+    for t in diagram.state_transition_out.values().flatten() {
+        let Some(path) = edge_path(diagram, aux, options, t) else {
+            continue;
+        };
+        let owner = match options.edges {
+            Edges::Shared => state_id![],
+            Edges::Nested if diagram.is_composite(&t.0) => t.0.clone(),
+            Edges::Nested => StateId(t.0 .0.range(..t.0 .0.len() - 1).cloned().collect()),
+        };
+        let event = variant(&owner, path.last());
+        if let Some((other, _)) = edges
+            .iter()
+            .find(|(p, e)| **e == event && **p != Some(path.clone()))
+        {
+            let describe = |path: &Option<Vec<String>>| match path {
+                Some(path) => format!("`{}`", path.join("::")),
+                None => "unlabelled transitions".to_string(),
+            };
+            bail!(
+                "the table backend names both {} and {} `EventId::{}`",
+                describe(other),
+                describe(&Some(path)),
+                event
+            );
+        }
+        edges.insert(Some(path), event);
+    }
+    let events: Vec<String> = iter::once("Completion".to_string())
+        .chain(
+            edges
+                .values()
+                .filter(|e| *e != "Completion")
+                .cloned()
+                .collect::<BTreeSet<_>>(),
+        )
+        .collect();
+    let event_id =
+        |path: &Option<Vec<String>>| events.iter().position(|e| *e == edges[path]).unwrap();
 
-    return quote! {
-        const _this_triggers_rebuild_on_change: &'static str = include_str!(#file_path_str);
-        pub struct Machine {
+    let mut table = vec![vec![None; events.len()]; states.len()];
+    for t in diagram.state_transition_out.values().flatten() {
+        if aux.nondeterministic_transitions.contains(t) {
+            bail!(
+                "the table backend cannot encode the guarded transition `{}`",
+                t.display_name()
+            );
         }
+        let from = aux
+            .transition_from_end_redirection
+            .get(t)
+            .copied()
+            .unwrap_or(&t.0);
+        let to = aux
+            .transition_to_start_redirection
+            .get(t)
+            .copied()
+            .unwrap_or(&t.1);
+        // Each edge type is its own event, and a state with two targets for one edge type is
+        // rejected above as guarded, so no cell of the table is written twice.
+        let event = event_id(&edge_path(diagram, aux, options, t));
+        table[state_ids[from]][event] = Some(state_ids[to]);
     }
-    .into();
+
+    let state_repr = repr(states.len());
+    let event_repr = repr(events.len());
+    let state_count = states.len();
+    let event_count = events.len();
+    let state_variants: Vec<Ident> = variants.iter().map(|v| ident(v)).collect();
+    let event_variants: Vec<Ident> = events.iter().map(|e| ident(e)).collect();
+    let rows = table.iter().map(|row| {
+        let cells = row.iter().map(|cell| match cell {
+            Some(to) => {
+                let to = &state_variants[*to];
+                quote! { ::core::option::Option::Some(StateId::#to) }
+            }
+            None => quote! { ::core::option::Option::None },
+        });
+        quote! { [#(#cells),*] }
+    });
+    let state_impls = states.iter().zip(&state_variants).map(|(s, variant)| {
+        let path = diagram.type_path(s).into_iter().map(|s| ident(&s));
+        quote! {
+            impl Id for super::#(#path)::* {
+                const ID: StateId = StateId::#variant;
+            }
+        }
+    });
+    let event_impls = edges.keys().map(|path| {
+        let event = &event_variants[event_id(path)];
+        let ty = match path {
+            Some(path) => {
+                let path = path.iter().map(|s| ident(s));
+                quote! { super::#(#path)::* }
+            }
+            None => quote! { () },
+        };
+        quote! {
+            impl Event for #ty {
+                const ID: EventId = EventId::#event;
+            }
+        }
+    });
+    Ok(quote! {
+        /// Dense ids of the flattened machine and a `const` table of its transitions, for
+        /// dispatching on a state only known at runtime.
+        #vis mod dense {
+            /// A state of the flattened machine, in the order of `STATES`.
+            #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+            #[repr(#state_repr)]
+            #vis enum StateId {
+                #(#state_variants),*
+            }
+
+            /// An edge; every unlabelled transition is a `Completion`.
+            #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+            #[repr(#event_repr)]
+            #vis enum EventId {
+                #(#event_variants),*
+            }
+
+            impl StateId {
+                #vis const COUNT: usize = #state_count;
+                #vis const ALL: [StateId; #state_count] = [#(StateId::#state_variants),*];
+
+                /// The state `event` leads to, if there is a transition for it.
+                #vis const fn next(self, event: EventId) -> ::core::option::Option<StateId> {
+                    TABLE[self as usize][event as usize]
+                }
+
+                #vis const fn info(self) -> &'static zero_cost_state_machine::table::StateInfo {
+                    &super::STATES[self as usize]
+                }
+            }
+
+            impl EventId {
+                #vis const COUNT: usize = #event_count;
+                #vis const ALL: [EventId; #event_count] = [#(EventId::#event_variants),*];
+            }
+
+            /// The transitions of the flattened machine, indexed by state and event.
+            #vis const TABLE: [[::core::option::Option<StateId>; EventId::COUNT]; StateId::COUNT] = [#(#rows),*];
+
+            /// The id of a `node` type of the flattened machine.
+            #vis trait Id {
+                const ID: StateId;
+            }
+
+            /// The id of an `edge` type.
+            #vis trait Event {
+                const ID: EventId;
+            }
+
+            #(#state_impls)*
+            #(#event_impls)*
+        }
+    })
 }
@@ -0,0 +1,287 @@
+use proc_macro2::Span;
+use std::collections::BTreeMap;
+use syn::parse::{Parse, ParseStream, Parser};
+use syn::punctuated::Punctuated;
+use syn::{parse_quote, Error, Ident, LitStr, Path, Result, Token, Type, Visibility};
+use zero_cost_state_machine_mermaid::{Diagram, Directive};
+
+/// What the macro does with the warnings of [`Diagram::validate`].
+///
+/// [`Diagram::validate`]: zero_cost_state_machine_mermaid::Diagram::validate
+#[derive(Debug, Default, Eq, PartialEq, Clone, Copy)]
+pub enum LintLevel {
+    Allow,
+    /// Report each warning as a use of a deprecated item.
+    #[default]
+    Warn,
+    /// Report each warning as a `compile_error!`.
+    Deny,
+}
+
+/// How unlabelled transitions out of one state are told apart.
+#[derive(Debug, Default, Eq, PartialEq, Clone, Copy)]
+pub enum CompletionEdges {
+    /// Every unlabelled transition is `Path<()>`; two out of one state are an error.
+    #[default]
+    Unit,
+    /// Unlabelled transitions out of a state with several of them get an `edge::To<Target>` type.
+    Named,
+}
+
+/// Where the `edge` types are defined.
+#[derive(Debug, Default, Eq, PartialEq, Clone, Copy)]
+pub enum Edges {
+    /// Each module defines the edges of its own transitions.
+    #[default]
+    Nested,
+    /// Each edge is defined once at the machine root and re-exported by the modules using it.
+    Shared,
+}
+
+/// How the machine is driven at runtime.
+#[derive(Debug, Default, Eq, PartialEq, Clone, Copy)]
+pub enum Backend {
+    /// Only the typestate API.
+    #[default]
+    Typestate,
+    /// The typestate API, plus a `dense` module of `repr` ids and a `const` lookup table for
+    /// dynamic dispatch.
+    Table,
+}
+
+/// Code generation options, read from the `%% @zcsm key value` directives of the diagram and
+/// then from the `key = value` pairs given after it, so the call site has the last word.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Options {
+    pub lints: LintLevel,
+    pub completion_edges: CompletionEdges,
+    pub edges: Edges,
+    pub backend: Backend,
+    /// Extra derives for the `node` and `edge` types and `State`.
+    pub derives: Vec<Path>,
+    /// Visibility of the generated modules and types.
+    pub visibility: Visibility,
+    /// Types carried by edges, keyed by the name of the `edge` type.
+    pub payloads: BTreeMap<String, Type>,
+    /// A module to generate the machine in, rather than in the scope of the macro call.
+    pub module: Option<Ident>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            lints: Default::default(),
+            completion_edges: Default::default(),
+            edges: Default::default(),
+            backend: Default::default(),
+            derives: vec![],
+            visibility: parse_quote! { pub },
+            payloads: BTreeMap::new(),
+            module: None,
+        }
+    }
+}
+
+const KEYS: &str = "`lints`, `completion_edges`, `edges`, `backend`, `module`";
+const DIRECTIVE_KEYS: &str = "`lints`, `completion_edges`, `edges`, `backend`, `module`, \
+    `derive`, `visibility`, `payload`, `rename`, `rename-edge`";
+
+impl Options {
+    /// Sets one of the options that both directives and the macro call accept.
+    fn set(&mut self, key: &str, value: &str, span: Span) -> Result<bool> {
+        match key {
+            "lints" => {
+                self.lints = keyword(
+                    value,
+                    span,
+                    &[
+                        ("allow", LintLevel::Allow),
+                        ("warn", LintLevel::Warn),
+                        ("deny", LintLevel::Deny),
+                    ],
+                )?
+            }
+            "completion_edges" => {
+                self.completion_edges = keyword(
+                    value,
+                    span,
+                    &[
+                        ("unit", CompletionEdges::Unit),
+                        ("named", CompletionEdges::Named),
+                    ],
+                )?
+            }
+            "edges" => {
+                self.edges = keyword(
+                    value,
+                    span,
+                    &[("nested", Edges::Nested), ("shared", Edges::Shared)],
+                )?
+            }
+            "backend" => {
+                self.backend = keyword(
+                    value,
+                    span,
+                    &[("typestate", Backend::Typestate), ("table", Backend::Table)],
+                )?
+            }
+            "module" => {
+                let module: Ident = syn::parse_str(value)?;
+                self.module = Some(Ident::new(&module.to_string(), span));
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// Applies the `config.zcsm` entries of the diagram front matter, e.g. `zcsm.lints` or
+    /// `zcsm.payload.Saved`; errors are reported at `span`.
+    pub fn apply_config(&mut self, config: &BTreeMap<String, String>, span: Span) -> Result<()> {
+        for (key, value) in config {
+            let Some(key) = key.strip_prefix("zcsm.") else {
+                continue;
+            };
+            match key.split_once('.') {
+                Some(("payload", edge)) => {
+                    self.apply("config", "payload", &format!("{} = {}", edge, value), span)?
+                }
+                _ => self.apply("config", key, value, span)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies the `%% @zcsm` directives of a diagram; errors are reported at `span`.
+    pub fn apply_directives(&mut self, directives: &[Directive], span: Span) -> Result<()> {
+        for Directive { key, value } in directives {
+            self.apply("directive", key, value, span)?;
+        }
+        Ok(())
+    }
+
+    fn apply(&mut self, source: &str, key: &str, value: &str, span: Span) -> Result<()> {
+        let error = |e: Error| Error::new(span, format!("{} `{}`: {}", source, key, e));
+        match key {
+            "derive" => {
+                let derives = Punctuated::<Path, Token![,]>::parse_terminated
+                    .parse_str(value)
+                    .map_err(error)?;
+                self.derives.extend(derives);
+            }
+            "visibility" => {
+                let visibility = syn::parse_str(value).map_err(error)?;
+                if !is_absolute(&visibility) {
+                    return Err(error(Error::new(
+                        span,
+                        "expected `pub`, `pub(crate)` or `pub(in crate::path)`, which mean the same \
+                         in every generated module",
+                    )));
+                }
+                self.visibility = visibility;
+            }
+            "payload" => {
+                let Some((edge, ty)) = value.split_once('=') else {
+                    return Err(error(Error::new(span, "expected `Edge = Type`")));
+                };
+                let ty = syn::parse_str(ty).map_err(error)?;
+                self.payloads.insert(edge.trim().into(), ty);
+            }
+            "rename" | "rename-edge" => {
+                return Err(error(Error::new(
+                    span,
+                    "expected `rename state = Name` or `rename-edge \"label\" = Name` in a directive",
+                )));
+            }
+            key => {
+                if !self.set(key, value, span).map_err(error)? {
+                    return Err(Error::new(
+                        span,
+                        format!(
+                            "unknown {} `{}`, expected one of {}",
+                            source, key, DIRECTIVE_KEYS
+                        ),
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies the `key = value` pairs given after the diagram.
+    pub fn apply_pairs(&mut self, pairs: &[(Ident, Ident)]) -> Result<()> {
+        for (key, value) in pairs {
+            if !self.set(&key.to_string(), &value.to_string(), value.span())? {
+                return Err(Error::new(
+                    key.span(),
+                    format!("unknown option, expected one of {}", KEYS),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether `visibility` names the same scope from any module, unlike `pub(self)`, `pub(super)`
+/// or an inherited one, which the nested modules of the machine would each read differently.
+fn is_absolute(visibility: &Visibility) -> bool {
+    match visibility {
+        Visibility::Public(_) => true,
+        Visibility::Restricted(restricted) => restricted
+            .path
+            .segments
+            .first()
+            .is_some_and(|segment| segment.ident == "crate"),
+        Visibility::Inherited => false,
+    }
+}
+
+fn keyword<T: Copy>(value: &str, span: Span, choices: &[(&str, T)]) -> Result<T> {
+    choices
+        .iter()
+        .find(|(name, _)| value == *name)
+        .map(|(_, choice)| *choice)
+        .ok_or_else(|| {
+            let names: Vec<_> = choices
+                .iter()
+                .map(|(name, _)| format!("`{}`", name))
+                .collect();
+            Error::new(span, format!("expected one of {}", names.join(", ")))
+        })
+}
+
+/// The diagram text followed by `, key = value` pairs.
+pub struct MacroInput {
+    pub contents: LitStr,
+    pub pairs: Vec<(Ident, Ident)>,
+}
+
+impl MacroInput {
+    /// The options for `diagram`: its front matter config, overridden by its directives,
+    /// overridden by the pairs of the call.
+    pub fn options(&self, diagram: &Diagram) -> Result<Options> {
+        let mut options = Options::default();
+        options.apply_config(&diagram.config, self.contents.span())?;
+        options.apply_directives(&diagram.directives, self.contents.span())?;
+        options.apply_pairs(&self.pairs)?;
+        Ok(options)
+    }
+}
+
+impl Parse for MacroInput {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let contents: LitStr = input.parse()?;
+        let mut pairs = vec![];
+        while !input.is_empty() {
+            input.parse::<Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+            let key: Ident = input.parse()?;
+            input.parse::<Token![=]>()?;
+            let value: Ident = input.parse()?;
+            pairs.push((key, value));
+        }
+        Options::default().apply_pairs(&pairs)?;
+        Ok(MacroInput { contents, pairs })
+    }
+}
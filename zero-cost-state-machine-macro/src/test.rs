@@ -1,14 +1,10 @@
+use crate::options::{Backend, CompletionEdges, Edges, LintLevel, MacroInput, Options};
 use crate::Aux;
 use maplit::btreemap;
 use pretty_assertions::assert_eq;
-use std::collections::{BTreeMap, VecDeque};
-use zero_cost_state_machine_mermaid::frame;
-use zero_cost_state_machine_mermaid::frames;
-use zero_cost_state_machine_mermaid::Frame;
-use zero_cost_state_machine_mermaid::Frames;
-use zero_cost_state_machine_mermaid::StateId;
-use zero_cost_state_machine_mermaid::TransitionId;
+use std::collections::{BTreeMap, BTreeSet};
 use zero_cost_state_machine_mermaid::{state_id, transition_id};
+use zero_cost_state_machine_mermaid::{Diagram, DiagramBuilder};
 
 fn keys_by_reference<K, V>(m: &BTreeMap<K, V>) -> BTreeMap<&K, V>
 where
@@ -46,7 +42,7 @@ fn exit_composite_state() -> anyhow::Result<()> {
     let (_, diagram) = zero_cost_state_machine_mermaid::human_readable_error(
         zero_cost_state_machine_mermaid::mermaid,
     )(contents)?;
-    let aux = Aux::new(&diagram)?;
+    let aux = Aux::new(&diagram, &Options::default())?;
     let transition_to_start_redirection = &btreemap! {
         transition_id!{["State1"]->["State2"]:"a"} => state_id!["State2",Start]
     };
@@ -100,6 +96,8 @@ fn exit_composite_state() -> anyhow::Result<()> {
             child_node_canonical_name: keys_by_reference(child_node_canonical_name),
             edge_canonical_name: keys_by_reference(edge_canonical_name),
             relative_canonical_name: keys_by_reference(relative_canonical_name),
+            cyclic_states: BTreeMap::new(),
+            nondeterministic_transitions: BTreeSet::new(),
         },
         aux
     );
@@ -126,7 +124,7 @@ fn fill_in_void() -> anyhow::Result<()> {
     let (_, diagram) = zero_cost_state_machine_mermaid::human_readable_error(
         zero_cost_state_machine_mermaid::mermaid,
     )(contents)?;
-    let aux = Aux::new(&diagram)?;
+    let aux = Aux::new(&diagram, &Options::default())?;
     let transition_to_start_redirection = &btreemap! {
         transition_id!{[Start]->["State1"]} => state_id!["State1",Start],
         transition_id!{["State2",Start]->["State2","State3"]} => state_id!["State2","State3",Start],
@@ -189,8 +187,336 @@ fn fill_in_void() -> anyhow::Result<()> {
             child_node_canonical_name: keys_by_reference(child_node_canonical_name),
             edge_canonical_name: keys_by_reference(edge_canonical_name),
             relative_canonical_name: keys_by_reference(relative_canonical_name),
+            cyclic_states: BTreeMap::new(),
+            nondeterministic_transitions: BTreeSet::new(),
         },
         aux
     );
     Ok(())
 }
+
+#[test]
+fn macro_options() -> syn::Result<()> {
+    let diagram = Diagram::default();
+    let input: MacroInput = syn::parse_str(r#""stateDiagram-v2", lints = deny,"#)?;
+    assert_eq!(LintLevel::Deny, input.options(&diagram)?.lints);
+    let input: MacroInput =
+        syn::parse_str(r#""stateDiagram-v2", completion_edges = named, lints = allow"#)?;
+    assert_eq!(
+        CompletionEdges::Named,
+        input.options(&diagram)?.completion_edges
+    );
+    assert_eq!(LintLevel::Allow, input.options(&diagram)?.lints);
+    let input: MacroInput = syn::parse_str(r#""stateDiagram-v2", edges = shared"#)?;
+    assert_eq!(Edges::Shared, input.options(&diagram)?.edges);
+    let input: MacroInput = syn::parse_str(r#""stateDiagram-v2", backend = table"#)?;
+    assert_eq!(Backend::Table, input.options(&diagram)?.backend);
+    let input: MacroInput = syn::parse_str(r#""stateDiagram-v2""#)?;
+    assert_eq!(Options::default(), input.options(&diagram)?);
+    assert!(syn::parse_str::<MacroInput>(r#""stateDiagram-v2", lints = loud"#).is_err());
+    assert!(syn::parse_str::<MacroInput>(r#""stateDiagram-v2", colour = red"#).is_err());
+    Ok(())
+}
+
+#[test]
+fn directive_options() -> syn::Result<()> {
+    let diagram = DiagramBuilder::new()
+        .directive("lints", "deny")
+        .directive("edges", "shared")
+        .directive("derive", "Debug, Clone")
+        .directive("derive", "Copy")
+        .directive("visibility", "pub(crate)")
+        .directive("payload", "Saved = Vec<u8>")
+        .build();
+    let input: MacroInput = syn::parse_str(r#""stateDiagram-v2", lints = warn"#)?;
+    let options = input.options(&diagram)?;
+    assert_eq!(LintLevel::Warn, options.lints);
+    assert_eq!(Edges::Shared, options.edges);
+    let derives: Vec<syn::Path> = vec![
+        syn::parse_quote! { Debug },
+        syn::parse_quote! { Clone },
+        syn::parse_quote! { Copy },
+    ];
+    assert_eq!(derives, options.derives);
+    let visibility: syn::Visibility = syn::parse_quote! { pub(crate) };
+    assert_eq!(visibility, options.visibility);
+    let diagram = DiagramBuilder::new()
+        .directive("visibility", "pub(in crate::outer)")
+        .build();
+    let visibility: syn::Visibility = syn::parse_quote! { pub(in crate::outer) };
+    assert_eq!(visibility, input.options(&diagram)?.visibility);
+    let payload: syn::Type = syn::parse_quote! { Vec<u8> };
+    assert_eq!(Some(&payload), options.payloads.get("Saved"));
+
+    let input: MacroInput = syn::parse_str(r#""stateDiagram-v2""#)?;
+    for (key, value) in [
+        ("colour", "red"),
+        ("lints", "loud"),
+        ("payload", "Saved"),
+        ("visibility", "pub(super)"),
+        ("visibility", "pub(self)"),
+        ("visibility", "pub(in super::outer)"),
+        ("visibility", ""),
+    ] {
+        let diagram = DiagramBuilder::new().directive(key, value).build();
+        assert!(input.options(&diagram).is_err());
+    }
+    Ok(())
+}
+
+#[test]
+fn config_options() -> syn::Result<()> {
+    let diagram = DiagramBuilder::new()
+        .config("theme", "forest")
+        .config("zcsm.lints", "deny")
+        .config("zcsm.module", "flow")
+        .config("zcsm.payload.Saved", "Vec<u8>")
+        .directive("lints", "allow")
+        .build();
+    let input: MacroInput = syn::parse_str(r#""stateDiagram-v2""#)?;
+    let options = input.options(&diagram)?;
+    assert_eq!(LintLevel::Allow, options.lints);
+    assert_eq!(
+        Some("flow".to_string()),
+        options.module.map(|m| m.to_string())
+    );
+    let payload: syn::Type = syn::parse_quote! { Vec<u8> };
+    assert_eq!(Some(&payload), options.payloads.get("Saved"));
+
+    let diagram = DiagramBuilder::new().config("zcsm.colour", "red").build();
+    assert!(input.options(&diagram).is_err());
+    Ok(())
+}
+
+#[test]
+fn merge_generated_items() {
+    let mut items: Vec<syn::Item> = vec![
+        syn::parse_quote! { fn helper() {} },
+        syn::parse_quote! { mod state3 { fn nested() {} } },
+    ];
+    let generated: syn::File = syn::parse_quote! {
+        pub mod node { pub struct Start; }
+        pub mod state3 { pub mod node { pub struct Long1; } }
+    };
+    crate::merge_items(&mut items, generated.items);
+    let expected: syn::File = syn::parse_quote! {
+        fn helper() {}
+        mod state3 {
+            fn nested() {}
+            pub mod node { pub struct Long1; }
+        }
+        pub mod node { pub struct Start; }
+    };
+    assert_eq!(expected.items, items);
+}
+
+#[test]
+fn zero_sized_states() -> anyhow::Result<()> {
+    let contents = r#"stateDiagram-v2
+        [*] --> Idle
+        Idle --> Busy : Start
+        Idle --> Saving : Save
+        Idle --> Parked : Park
+        state Busy {
+            [*] --> working
+            working --> [*]
+        }
+        state Saving {
+            [*] --> writing
+            writing --> [*]
+        }
+        Busy --> [*] : Done
+        Busy --> Paused : Pause
+        Saving --> Saved
+        "#;
+    let (_, diagram) = zero_cost_state_machine_mermaid::human_readable_error(
+        zero_cost_state_machine_mermaid::mermaid,
+    )(contents)?;
+    let input: MacroInput = syn::parse_str(r#""", edges = shared"#)?;
+    let mut options = input.options(&diagram)?;
+    for payload in ["Save", "Park", "Pause"] {
+        options
+            .payloads
+            .insert(payload.into(), syn::parse_quote! { u8 });
+    }
+    let aux = Aux::new(&diagram, &options)?;
+    let asserted = crate::zero_sized(&diagram, &aux, &options).to_string();
+    let size_of =
+        |ty: proc_macro2::TokenStream| quote::quote! { ::core::mem::size_of::<#ty>() }.to_string();
+    assert!(asserted.contains(&size_of(quote::quote! { busy::node::Working })));
+    assert!(asserted.contains(&size_of(quote::quote! { edge::Start })));
+    assert!(asserted.contains(&size_of(quote::quote! { State<node::Idle> })));
+    assert!(asserted.contains(&size_of(quote::quote! {
+        busy::State<node::Idle, edge::Start, busy::node::Working>
+    })));
+    assert!(asserted.contains(&size_of(quote::quote! { State<node::End> })));
+    assert!(!asserted.contains(&size_of(quote::quote! { edge::Save })));
+    assert!(!asserted.contains("saving :: State"));
+    // Payloads taken on the same level or out of a composite are not stored in the state.
+    assert!(asserted.contains(&size_of(quote::quote! { State<node::Parked> })));
+    assert!(asserted.contains(&size_of(quote::quote! { State<node::Paused> })));
+    assert!(asserted.contains(&size_of(quote::quote! { State<node::Saved> })));
+    assert!(!asserted.contains(&size_of(quote::quote! { edge::Park })));
+    Ok(())
+}
+
+#[test]
+fn completion_edge_collides_with_label() -> anyhow::Result<()> {
+    let parse = |contents| {
+        zero_cost_state_machine_mermaid::human_readable_error(
+            zero_cost_state_machine_mermaid::mermaid,
+        )(contents)
+        .map(|(_, diagram)| diagram)
+    };
+    let options = Options {
+        completion_edges: CompletionEdges::Named,
+        ..Options::default()
+    };
+    let diagram = parse(
+        r#"stateDiagram-v2
+        [*] --> A
+        A --> B
+        A --> C
+        B --> C : To C
+        "#,
+    )?;
+    let error = Aux::new(&diagram, &options).err().unwrap().to_string();
+    assert!(error.contains("`A --> C` is named `edge::ToC`, as is the edge of `B --> C : To C`"));
+    let diagram = parse(
+        r#"stateDiagram-v2
+        [*] --> A
+        A --> B
+        A --> C
+        B --> C : Go
+        "#,
+    )?;
+    assert!(Aux::new(&diagram, &options).is_ok());
+    Ok(())
+}
+
+#[test]
+fn dense_table() -> anyhow::Result<()> {
+    let dense = |contents: &str| -> anyhow::Result<String> {
+        let (_, diagram) = zero_cost_state_machine_mermaid::human_readable_error(
+            zero_cost_state_machine_mermaid::mermaid,
+        )(contents)?;
+        let input: MacroInput = syn::parse_str(r#""", backend = table"#)?;
+        let options = input.options(&diagram)?;
+        let aux = Aux::new(&diagram, &options)?;
+        Ok(crate::dense(&diagram, &aux, &options)?.to_string())
+    };
+    let error = |contents: &str| dense(contents).err().unwrap().to_string();
+
+    // Equal labels in different composites are different edge types, and so different events.
+    let generated = dense(
+        r#"stateDiagram-v2
+        [*] --> A
+        state A {
+            [*] --> a
+            a --> b : Go
+            b --> [*]
+        }
+        A --> B : Go
+        state B {
+            [*] --> c
+            c --> d : Go
+        }
+        "#,
+    )?;
+    let event = |ty: proc_macro2::TokenStream, id: proc_macro2::TokenStream| {
+        quote::quote! { impl Event for super::#ty { const ID: EventId = EventId::#id; } }
+            .to_string()
+    };
+    assert!(generated.contains(&event(quote::quote! { a::edge::Go }, quote::quote! { AGo })));
+    assert!(generated.contains(&event(quote::quote! { b::edge::Go }, quote::quote! { BGo })));
+    assert!(generated.contains("enum EventId { Completion , AGo , BGo }"));
+
+    assert!(error(
+        r#"stateDiagram-v2
+        [*] --> Dense
+        state Dense {
+            [*] --> a
+        }
+        "#
+    )
+    .contains("generates a `dense` module, which a composite state is named as"));
+    assert!(error(
+        r#"stateDiagram-v2
+        [*] --> A
+        A --> AInner
+        state A {
+            [*] --> inner
+            inner --> [*]
+        }
+        "#
+    )
+    .contains("names two states `StateId::AInner`"));
+    assert!(error(
+        r#"stateDiagram-v2
+        [*] --> A
+        A --> [*] : Completion
+        "#
+    )
+    .contains("names both unlabelled transitions and `edge::Completion` `EventId::Completion`"));
+    assert!(error(
+        r#"stateDiagram-v2
+        [*] --> A
+        A --> B : Go
+        state A {
+            [*] --> a
+            a --> [*]
+        }
+        B --> [*] : A Go
+        "#
+    )
+    .contains("names both `a::edge::Go` and"));
+    // Two targets for one event out of a state.
+    assert!(error(
+        r#"stateDiagram-v2
+        [*] --> A
+        A --> B : Go
+        A --> C : Go
+        "#
+    )
+    .contains("cannot encode the guarded transition `A --> B : Go`"));
+    Ok(())
+}
+
+#[test]
+fn title_names_module() -> syn::Result<()> {
+    let expand = |contents: &str, in_module: bool| -> syn::Result<String> {
+        let input: MacroInput = syn::parse_str(&format!("{:?}", contents))?;
+        Ok(crate::expand(&input, contents, in_module).to_string())
+    };
+    let titled =
+        |title: &str| format!("---\ntitle: {}\n---\nstateDiagram-v2\n[*] --> [*]\n", title);
+
+    assert!(expand(&titled("Order Flow"), false)?.contains("pub mod order_flow {"));
+    assert!(expand(&titled("match"), true)?.contains("pub mod r#match {"));
+    let untitled = expand("stateDiagram-v2\n[*] --> [*]\n", false)?;
+    assert!(!untitled.contains("compile_error") && untitled.contains("pub struct State"));
+    assert!(expand("stateDiagram-v2\n[*] --> [*]\n", true)?
+        .contains("the diagram has no title to name its module after"));
+    assert!(expand(&titled("'!!!'"), false)?
+        .contains(r#"the title \"!!!\" does not make a module name; pass `module = name`"#));
+    Ok(())
+}
+
+#[test]
+fn no_at_trait_for_composites() -> syn::Result<()> {
+    let contents = r#"
+        stateDiagram-v2
+        [*] --> State3
+        state State3 {
+          [*] --> long1
+          long1 --> [*]
+        }
+        State3 --> [*]
+    "#;
+    let input: MacroInput = syn::parse_str(&format!("{:?}", contents))?;
+    let expanded = crate::expand(&input, contents, false).to_string();
+    assert!(expanded.contains("pub trait AtLong1"));
+    assert!(expanded.contains("pub trait AtEnd"));
+    assert!(!expanded.contains("AtState3"));
+    Ok(())
+}
@@ -0,0 +1,25 @@
+use zero_cost_state_machine::Path;
+
+mod completions {
+    zero_cost_state_machine_macro::statemachine_from_mermaid! {
+        r#"
+            stateDiagram-v2
+            [*] --> Work
+            Work --> Done
+            Work --> Failed
+            Done --> [*]
+            Failed --> [*]
+        "#,
+        completion_edges = named
+    }
+}
+
+#[test]
+fn named_completion_edges() {
+    use completions::{edge, node, State};
+
+    let work = || State { head: node::Start }.transition(());
+    let State { head: node::Done } = work().transition(edge::ToDone);
+    let State { head: node::Failed } = work().transition(edge::ToFailed);
+    let State { head: node::End } = work().transition(edge::ToFailed).transition(());
+}
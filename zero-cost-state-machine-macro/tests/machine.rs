@@ -0,0 +1,32 @@
+#[zero_cost_state_machine_macro::machine("tests/flow.mmd")]
+mod flow {
+    impl State<node::Placed> {
+        pub fn cancel(self) -> State<node::End> {
+            self.transition(edge::Cancelled)
+        }
+    }
+
+    pub mod packing {
+        impl<N0, E0, S> State<N0, E0, S> {
+            pub fn order(&self) -> &N0 {
+                &self.node0
+            }
+        }
+    }
+
+    pub fn placed() -> State<node::Placed> {
+        State { head: node::Start }.transition(())
+    }
+}
+
+#[test]
+fn machine_attribute() {
+    use flow::{edge, node, packing, State};
+    use zero_cost_state_machine::Path;
+
+    let State { head: node::End } = flow::placed().cancel();
+    let picking = flow::placed().transition(edge::Paid).transition(());
+    let _: &node::Placed = picking.order();
+    let boxing = picking.transition(packing::edge::Picked);
+    let State { head: node::End } = boxing.transition(()).transition(packing::edge::Shipped);
+}
@@ -0,0 +1,32 @@
+use zero_cost_state_machine::Path;
+
+mod shared {
+    zero_cost_state_machine_macro::statemachine_from_mermaid! {
+        r#"
+            stateDiagram-v2
+            [*] --> Outer
+            Outer --> [*] : Aborted
+            state Outer {
+                [*] --> Inner
+                Inner --> [*] : Aborted
+                state Inner {
+                    [*] --> work
+                    work --> [*] : Aborted
+                }
+            }
+        "#,
+        edges = shared
+    }
+}
+
+#[test]
+fn shared_edges() {
+    use shared::{edge, node, outer, State};
+
+    // Every level names the one root edge type.
+    let aborted: edge::Aborted = outer::inner::edge::Aborted;
+    let s = State { head: node::Start }.transition(());
+    let s = s.transition(()).transition(());
+    let s = s.transition(aborted).transition(outer::edge::Aborted);
+    let State { head: node::End } = s.transition(edge::Aborted);
+}
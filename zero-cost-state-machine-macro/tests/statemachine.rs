@@ -1,5 +1,3 @@
-use zero_cost_state_machine_macro::statemachine_from_mermaid;
-
 // statemachine_from_puml! {
 //     r#"@startuml
 //     scale 600 width
@@ -27,7 +25,7 @@ use zero_cost_state_machine_macro::statemachine_from_mermaid;
 // statemachine_from_mermaid! {
 //     r#"@startuml
 //     scale 600 width
-// 
+//
 //     [*] -> State1
 //     state State2 {
 //         [*] -> State3
@@ -74,3 +72,390 @@ use zero_cost_state_machine_macro::statemachine_from_mermaid;
 fn foo() -> anyhow::Result<()> {
     Ok(())
 }
+
+mod guarded {
+    zero_cost_state_machine_macro::statemachine_from_mermaid! {
+        r#"
+            stateDiagram-v2
+            [*] --> Waiting
+            Waiting --> Retrying : Timeout
+            Waiting --> Failed : Timeout
+            state Retrying {
+                [*] --> sending
+                sending --> [*]
+            }
+            Retrying --> Waiting
+            Failed --> [*]
+        "#
+    }
+}
+
+#[test]
+fn guarded_transition_into_composite() {
+    use guarded::{edge, node, retrying, State};
+    use zero_cost_state_machine::{Path, TryPath};
+
+    let waiting = State { head: node::Start }.transition(());
+    let Err(waiting) = waiting.try_transition(edge::Timeout, node::Retrying, false) else {
+        panic!("the guard does not hold");
+    };
+    let Ok(retrying) = waiting.try_transition(edge::Timeout, node::Retrying, true) else {
+        panic!("the guard holds");
+    };
+    let retrying::State {
+        head: retrying::node::Start,
+        ..
+    } = retrying;
+    let sending = retrying.transition(());
+    let State {
+        head: node::Waiting,
+    } = sending.transition(()).transition(());
+}
+
+mod restricted {
+    pub mod machine {
+        zero_cost_state_machine_macro::statemachine_from_mermaid! {
+            r#"
+                stateDiagram-v2
+                %% @zcsm visibility pub(in crate::restricted)
+                [*] --> Busy
+                state Busy {
+                    [*] --> working
+                    working --> [*]
+                }
+                Busy --> [*]
+            "#
+        }
+    }
+
+    pub fn finish() -> bool {
+        use machine::{busy, node, State};
+        use zero_cost_state_machine::Path;
+        let working: busy::State<node::Start, (), busy::node::Working> =
+            State { head: node::Start }.transition(()).transition(());
+        let State { head: node::End } = working.transition(()).transition(());
+        machine::STATES.len() == 5
+    }
+}
+
+#[test]
+fn restricted_visibility() {
+    assert!(restricted::finish());
+}
+
+use zero_cost_state_machine::Path;
+
+mod nested {
+    zero_cost_state_machine_macro::statemachine_from_mermaid! {
+        r#"
+            stateDiagram-v2
+            [*] --> State1
+            State1 --> State2 : Succeeded
+            State1 --> [*] : Aborted
+            State2 --> State3 : Succeeded
+            State2 --> [*] : Aborted
+            state State3 {
+                state "Accumulate Enough Data\nLong State Name" as long1
+                long1 : Just a test
+                [*] --> long1
+                long1 --> long1 : New Data
+                long1 --> ProcessData : Enough Data
+                ProcessData --> [*]
+                long1 --> [*]
+            }
+            State3 --> State3 : Failed
+            State3 --> [*] : Succeeded / Save Result
+        "#
+    }
+}
+
+fn in_long1(
+) -> nested::state3::State<nested::node::State2, nested::edge::Succeeded, nested::state3::node::Long1>
+{
+    use nested::{edge, node, State};
+    let s = State { head: node::Start }.transition(());
+    let s = s.transition(edge::Succeeded).transition(edge::Succeeded);
+    s.transition(())
+}
+
+trait Accumulate {
+    fn accumulate(self) -> usize;
+}
+
+impl<S: nested::state3::AtLong1> Accumulate for S
+where
+    <S as Path<nested::state3::edge::NewData>>::Target: nested::state3::AtLong1,
+{
+    fn accumulate(self) -> usize {
+        let _again = self.transition(nested::state3::edge::NewData);
+        1
+    }
+}
+
+fn save<S: nested::state3::AtEnd>(
+    s: S,
+) -> <S as Path<nested::state3::edge::SucceededSaveResult>>::Target {
+    s.transition(nested::state3::edge::SucceededSaveResult)
+}
+
+#[test]
+fn at_traits() {
+    assert_eq!(1, in_long1().accumulate());
+    let nested::State {
+        head: nested::node::End,
+    } = save(in_long1().transition(()));
+}
+
+mod deep {
+    zero_cost_state_machine_macro::statemachine_from_mermaid! {
+        r#"
+            stateDiagram-v2
+            [*] --> Idle
+            Idle --> Outer : Enter
+            Idle --> Outer.Inner.work : Resume
+            state Outer {
+                [*] --> Inner
+                state Inner {
+                    [*] --> work
+                    work --> [*]
+                }
+                Inner --> [*]
+            }
+            Outer --> [*]
+        "#
+    }
+}
+
+#[test]
+fn breadcrumbs() {
+    use deep::{edge, node, outer, State};
+    use zero_cost_state_machine::{Breadcrumbs, Crumb, NoEdge, NoNode};
+
+    let s = in_long1();
+    let _: &nested::node::State2 = s.entered_from();
+    let _: &nested::edge::Succeeded = s.entry_edge();
+    let Crumb {
+        from: nested::node::State2,
+        edge: nested::edge::Succeeded,
+        parent: (),
+    } = s.ancestry();
+    let () = s.parent();
+
+    let idle = || State { head: node::Start }.transition(());
+    let s = idle().transition(edge::Enter).transition(()).transition(());
+    let _: &outer::inner::node::Work = &s.head;
+    let _: &outer::node::Start = s.entered_from();
+    let Crumb {
+        from: outer::node::Start,
+        edge: (),
+        parent:
+            Crumb {
+                from: node::Idle,
+                edge: edge::Enter,
+                parent: (),
+            },
+    } = s.ancestry();
+
+    // Entering both composites at once leaves the inner one without a node or edge.
+    let s = idle().transition(edge::Resume);
+    let _: &outer::inner::node::Work = &s.head;
+    let _: &NoNode = s.entered_from();
+    let _: &NoEdge = s.entry_edge();
+    let Crumb {
+        from: NoNode,
+        edge: NoEdge,
+        parent:
+            Crumb {
+                from: node::Idle,
+                edge: edge::Resume,
+                parent: (),
+            },
+    } = s.ancestry();
+}
+
+fn inside_outer<S: deep::InOuter>(_: &S) -> bool {
+    true
+}
+
+fn inside_inner<S: deep::outer::InInner>(_: &S) -> bool {
+    true
+}
+
+fn inside_state3<S: nested::InState3>(_: &S) -> bool {
+    true
+}
+
+#[test]
+fn in_traits() {
+    use deep::{edge, node, State};
+
+    let s = State { head: node::Start }.transition(());
+    let s = s.transition(edge::Enter);
+    assert!(inside_outer(&s));
+    let s = s.transition(()).transition(());
+    assert!(inside_outer(&s) && inside_inner(&s));
+    assert!(inside_state3(&in_long1()));
+}
+
+fn can_finish<S: zero_cost_state_machine::CanReach<nested::node::End>>(_: &S) -> bool {
+    true
+}
+
+fn can_accumulate<S>(_: &S) -> bool
+where
+    S: zero_cost_state_machine::CanReach<nested::state3::node::Long1>
+        + zero_cost_state_machine::CanReach<nested::node::State3>,
+{
+    true
+}
+
+#[test]
+fn can_reach() {
+    use nested::{node, State};
+
+    assert!(can_finish(&State { head: node::Start }));
+    assert!(can_accumulate(&State { head: node::State1 }));
+    assert!(can_accumulate(&State { head: node::State2 }));
+    assert!(can_finish(&in_long1()) && can_accumulate(&in_long1()));
+}
+
+#[test]
+fn state_and_edge_info() {
+    use nested::{edge, node, state3};
+    use zero_cost_state_machine::{EdgeInfo, StateInfo};
+
+    assert_eq!("Long1", state3::node::Long1::NAME);
+    assert_eq!(
+        Some("Accumulate Enough Data\nLong State Name"),
+        state3::node::Long1::ALIAS
+    );
+    assert_eq!(&["State3", "long1"], state3::node::Long1::PATH);
+    assert_eq!(1, state3::node::Long1::DEPTH);
+    assert_eq!(&["Just a test"], state3::node::Long1::DESCRIPTION);
+    assert_eq!(None, node::State1::ALIAS);
+    assert_eq!((true, false), (node::Start::IS_START, node::Start::IS_END));
+    assert_eq!(
+        (false, true),
+        (state3::node::End::IS_START, state3::node::End::IS_END)
+    );
+    assert_eq!(&["[*]"], node::End::PATH);
+    assert_eq!(
+        "Succeeded / Save Result",
+        state3::edge::SucceededSaveResult::LABEL
+    );
+    assert_eq!("Succeeded", edge::Succeeded::LABEL);
+    assert_eq!("", <() as EdgeInfo>::LABEL);
+}
+
+#[test]
+fn tables() {
+    use zero_cost_state_machine::table::{CycleInfo, TransitionInfo};
+
+    let nodes: Vec<_> = nested::STATES.iter().map(|s| s.node).collect();
+    assert_eq!(
+        vec![
+            "node::Start",
+            "node::End",
+            "node::State1",
+            "node::State2",
+            "state3::node::Start",
+            "state3::node::End",
+            "state3::node::ProcessData",
+            "state3::node::Long1",
+        ],
+        nodes
+    );
+    let long1 = nested::STATES[7];
+    assert_eq!(&["State3", "long1"], long1.path);
+    assert_eq!(Some("Accumulate Enough Data\nLong State Name"), long1.alias);
+    assert_eq!(
+        (1, false, false),
+        (long1.depth, long1.is_start, long1.is_end)
+    );
+
+    assert_eq!(12, nested::TRANSITIONS.len());
+    assert!(nested::TRANSITIONS.contains(&TransitionInfo {
+        from: "node::State2",
+        to: "state3::node::Start",
+        label: Some("Succeeded"),
+        edge: "edge::Succeeded",
+        guarded: false,
+    }));
+    assert!(nested::TRANSITIONS.contains(&TransitionInfo {
+        from: "state3::node::End",
+        to: "state3::node::Start",
+        label: Some("Failed"),
+        edge: "state3::edge::Failed",
+        guarded: false,
+    }));
+    assert!(nested::TRANSITIONS.contains(&TransitionInfo {
+        from: "state3::node::Long1",
+        to: "state3::node::End",
+        label: None,
+        edge: "()",
+        guarded: false,
+    }));
+
+    assert_eq!(
+        &[CycleInfo {
+            states: &["state3::node::ProcessData", "state3::node::Long1"],
+            bounded: false,
+        }],
+        nested::CYCLES
+    );
+    assert!(deep::CYCLES.is_empty());
+}
+
+mod rally {
+    zero_cost_state_machine_macro::statemachine_from_mermaid! {
+        r#"
+            stateDiagram-v2
+            [*] --> Idle
+            Idle --> Idle : Tick
+            Idle --> Ping : Serve
+            Ping --> Pong : Hit
+            Pong --> Ping : Hit
+            Pong --> [*] : Miss
+        "#
+    }
+}
+
+#[test]
+fn bounded_and_unbounded_cycles() {
+    use zero_cost_state_machine::table::CycleInfo;
+
+    assert_eq!(
+        &[
+            CycleInfo {
+                states: &["node::Idle"],
+                bounded: false,
+            },
+            CycleInfo {
+                states: &["node::Ping", "node::Pong"],
+                bounded: true,
+            },
+        ],
+        rally::CYCLES
+    );
+}
+
+#[test]
+fn mermaid() {
+    use zero_cost_state_machine::{to_mermaid, Mermaid};
+
+    assert!(nested::DIAGRAM.trim_start().starts_with("stateDiagram-v2"));
+    assert!(nested::DIAGRAM.contains("long1 --> long1 : New Data"));
+    let shown = to_mermaid::<nested::state3::node::Long1>(nested::DIAGRAM).to_string();
+    assert!(shown.starts_with(nested::DIAGRAM.trim_end()));
+    assert!(shown.ends_with("    class long1 active\n"));
+    let shown = to_mermaid::<nested::node::State2>(nested::DIAGRAM).to_string();
+    assert!(shown.ends_with("    class State2 active\n"));
+    assert!(!to_mermaid::<nested::node::Start>(nested::DIAGRAM)
+        .to_string()
+        .contains("class "));
+    let long1 = nested::STATES[7];
+    assert_eq!(
+        to_mermaid::<nested::state3::node::Long1>(nested::DIAGRAM).to_string(),
+        Mermaid::new(nested::DIAGRAM).active(long1.path).to_string()
+    );
+}
@@ -1,5 +1,5 @@
 use crate::scope::Scope;
-use anyhow::anyhow;
+use anyhow::{anyhow, bail};
 use nom::branch::alt;
 use nom::bytes::complete::{
     escaped_transform, is_not, tag, tag_no_case, take_till, take_until, take_while1,
@@ -10,11 +10,11 @@ use nom::error::{convert_error, VerboseError};
 use nom::multi::{many0, many_till, separated_list1};
 use nom::sequence::{delimited, preceded, terminated, tuple};
 use nom::IResult;
-use std::collections::VecDeque;
+use std::collections::{BTreeMap, VecDeque};
 use std::fmt::Debug;
 use std::ops::Deref;
 
-pub use internal::*;
+pub use zero_cost_state_machine_ir::*;
 
 mod scope;
 
@@ -65,16 +65,132 @@ fn enduml(input: &str) -> IResult<&str, &str, VerboseError<&str>> {
     delimited(space0, tag("@enduml"), multispace0)(input)
 }
 
-fn title(input: &str) -> IResult<&str, Vec<&str>, VerboseError<&str>> {
-    delimited(
-        delimited(multispace0, tag("---"), line_ending),
-        many0(delimited(
-            delimited(multispace0, token1, multispace0),
-            description,
-            line_ending,
-        )),
+/// The `title` and `config` of the front matter.
+#[derive(Default)]
+struct FrontMatter {
+    title: Option<String>,
+    config: BTreeMap<String, String>,
+}
+
+/// A `key: value` line of the front matter, with its indentation.
+fn front_matter_entry(input: &str) -> IResult<&str, (usize, &str, &str), VerboseError<&str>> {
+    tuple((
+        map(space0, str::len),
+        take_while1(|c: char| c.is_alphanumeric() || c == '_' || c == '-'),
+        preceded(
+            tuple((space0, tag(":"), space0)),
+            map(opt(is_not("\n")), |v: Option<&str>| v.unwrap_or_default().trim()),
+        ),
+    ))(input)
+}
+
+/// A `- value` line of a block sequence in the front matter, with its indentation.
+fn front_matter_item(input: &str) -> IResult<&str, (usize, &str), VerboseError<&str>> {
+    tuple((
+        map(space0, str::len),
+        preceded(tuple((tag("-"), space1)), map(is_not("\n"), str::trim)),
+    ))(input)
+}
+
+/// A scalar of the front matter without its quotes or trailing `# comment`.
+fn front_matter_scalar(value: &str) -> &str {
+    let value = value.trim();
+    if let Some(quoted) = ['"', '\''].iter().find_map(|q| {
+        let rest = value.strip_prefix(*q)?;
+        Some(&rest[..rest.find(*q)?])
+    }) {
+        return quoted;
+    }
+    match value.find(" #") {
+        Some(comment) => value[..comment].trim_end(),
+        None if value.starts_with('#') => "",
+        None => value,
+    }
+}
+
+/// A value of the front matter: a scalar, or a flow sequence `[a, b]` read as `a, b`.
+fn front_matter_value(value: &str) -> String {
+    let value = front_matter_scalar(value);
+    match value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+        Some(items) => items
+            .split(',')
+            .map(front_matter_scalar)
+            .collect::<Vec<_>>()
+            .join(", "),
+        None => value.to_string(),
+    }
+}
+
+/// A line of the front matter.
+enum FrontMatterLine<'a> {
+    Entry(usize, &'a str, &'a str),
+    Item(usize, &'a str),
+}
+
+/// The YAML front matter, read as far as nested mappings of scalars go. Block and flow
+/// sequences of scalars are read as one comma separated value, and comments are skipped.
+fn front_matter(input: &str) -> IResult<&str, FrontMatter, VerboseError<&str>> {
+    let (input, lines) = delimited(
         delimited(multispace0, tag("---"), line_ending),
-    )(input)
+        many0(alt((
+            map(
+                terminated(front_matter_item, line_ending),
+                |(indent, value)| Some(FrontMatterLine::Item(indent, value)),
+            ),
+            map(
+                terminated(front_matter_entry, line_ending),
+                |(indent, key, value)| Some(FrontMatterLine::Entry(indent, key, value)),
+            ),
+            map(
+                terminated(
+                    tuple((space0, opt(preceded(tag("#"), opt(is_not("\n")))))),
+                    line_ending,
+                ),
+                |_| None,
+            ),
+        ))),
+        delimited(space0, tag("---"), line_ending),
+    )(input)?;
+    let mut front_matter = FrontMatter::default();
+    let mut parents: Vec<(usize, &str)> = vec![];
+    let mut set = |path: &[&str], value: String, append: bool| match path {
+        ["title"] => front_matter.title = Some(value),
+        ["config", rest @ ..] => {
+            let entry = front_matter.config.entry(rest.join(".")).or_default();
+            if append && !entry.is_empty() {
+                entry.push_str(", ");
+                entry.push_str(&value);
+            } else {
+                *entry = value;
+            }
+        }
+        _ => {}
+    };
+    for line in lines.into_iter().flatten() {
+        match line {
+            FrontMatterLine::Entry(indent, key, value) => {
+                while parents.last().is_some_and(|(i, _)| *i >= indent) {
+                    parents.pop();
+                }
+                let value = front_matter_value(value);
+                if value.is_empty() {
+                    parents.push((indent, key));
+                    continue;
+                }
+                let path: Vec<_> = parents.iter().map(|(_, k)| *k).chain([key]).collect();
+                set(&path, value, false);
+            }
+            // Items of a sequence may be indented as far as its key.
+            FrontMatterLine::Item(indent, value) => {
+                while parents.last().is_some_and(|(i, _)| *i > indent) {
+                    parents.pop();
+                }
+                let path: Vec<_> = parents.iter().map(|(_, k)| *k).collect();
+                set(&path, front_matter_scalar(value).to_string(), true);
+            }
+        }
+    }
+    Ok((input, front_matter))
 }
 
 fn comment(input: &str) -> IResult<&str, &str, VerboseError<&str>> {
@@ -183,6 +299,14 @@ enum SpecialStateName {
     History,
     DeepHistory,
 }
+type TransitionEndpoint = Either<
+    (
+        VecDeque<String>,
+        Option<StateModifier>,
+        Option<StateStereoType>,
+    ),
+    SpecialStateName,
+>;
 #[derive(Clone, Debug)]
 enum Item {
     ImplicitStateDeclaration {
@@ -199,22 +323,8 @@ enum Item {
         lines: Vec<Line>,
     },
     Transition {
-        from_name: Either<
-            (
-                VecDeque<String>,
-                Option<StateModifier>,
-                Option<StateStereoType>,
-            ),
-            SpecialStateName,
-        >,
-        to_name: Either<
-            (
-                VecDeque<String>,
-                Option<StateModifier>,
-                Option<StateStereoType>,
-            ),
-            SpecialStateName,
-        >,
+        from_name: TransitionEndpoint,
+        to_name: TransitionEndpoint,
         description: Option<String>,
     },
 }
@@ -363,7 +473,7 @@ fn item(input: &str) -> IResult<&str, Item, VerboseError<&str>> {
                         alt((
                             map(preceded(space0, scoped_token1_maybe_quote), |t| {
                                 t.into_iter()
-                                    .map(|s| String::from(s))
+                                    .map(String::from)
                                     .collect::<VecDeque<_>>()
                             }),
                             map(preceded(space0, token1_maybe_quote), |t| {
@@ -398,7 +508,7 @@ fn item(input: &str) -> IResult<&str, Item, VerboseError<&str>> {
                         alt((
                             map(preceded(space0, scoped_token1_maybe_quote), |t| {
                                 t.into_iter()
-                                    .map(|s| String::from(s))
+                                    .map(String::from)
                                     .collect::<VecDeque<_>>()
                             }),
                             map(preceded(space0, token1_maybe_quote), |t| {
@@ -487,7 +597,7 @@ fn note(input: &str) -> IResult<&str, Note, VerboseError<&str>> {
                         |(lines, _): (Vec<(Vec<char>, _)>, _)| {
                             lines
                                 .into_iter()
-                                .map(|v| String::from_iter(v.0.into_iter()))
+                                .map(|v| String::from_iter(v.0))
                                 .collect::<Vec<_>>()
                         },
                     ),
@@ -511,7 +621,7 @@ fn note(input: &str) -> IResult<&str, Note, VerboseError<&str>> {
                     |(lines, _): (Vec<(Vec<char>, _)>, _)| {
                         lines
                             .into_iter()
-                            .map(|v| String::from_iter(v.0.into_iter()))
+                            .map(|v| String::from_iter(v.0))
                             .collect::<Vec<_>>()
                     },
                 ),
@@ -591,8 +701,59 @@ fn concurrent_vertical(input: &str) -> IResult<&str, (), VerboseError<&str>> {
     map(preceded(space0, tag("||")), |_| ())(input)
 }
 
+#[derive(Clone, Debug)]
+enum Pragma {
+    Rename { logical: Vec<String>, name: String },
+    RenameEdge { label: String, name: String },
+    Directive(Directive),
+}
+
+/// `%% @zcsm ...` comments, which Mermaid ignores but which steer code generation.
+fn pragma(input: &str) -> IResult<&str, Pragma, VerboseError<&str>> {
+    preceded(
+        tuple((tag("%%"), space0, tag("@zcsm"), space1)),
+        alt((
+            map(
+                tuple((
+                    tag("rename-edge"),
+                    preceded(space1, alt((quoted_string, map(token1, String::from)))),
+                    preceded(delimited(space0, tag("="), space0), token1),
+                )),
+                |(_, label, name)| Pragma::RenameEdge {
+                    label,
+                    name: name.into(),
+                },
+            ),
+            map(
+                tuple((
+                    tag("rename"),
+                    preceded(space1, scoped_token1_maybe_quote),
+                    preceded(delimited(space0, tag("="), space0), token1),
+                )),
+                |(_, logical, name)| Pragma::Rename {
+                    logical: logical.into_iter().map(String::from).collect(),
+                    name: name.into(),
+                },
+            ),
+            map(
+                tuple((
+                    take_while1(|c: char| c.is_alphanumeric() || c == '_' || c == '-'),
+                    opt(preceded(space1, is_not("\n"))),
+                )),
+                |(key, value): (&str, Option<&str>)| {
+                    Pragma::Directive(Directive {
+                        key: key.into(),
+                        value: value.unwrap_or_default().trim().into(),
+                    })
+                },
+            ),
+        )),
+    )(input)
+}
+
 #[derive(Clone, Debug)]
 enum Line {
+    Pragma(Pragma),
     Style,
     JsonBlock,
     Directive,
@@ -609,6 +770,10 @@ enum Line {
 }
 fn line(input: &str) -> IResult<&str, Line, VerboseError<&str>> {
     alt((
+        map(
+            delimited(space0, terminated(pragma, space0), line_ending),
+            Line::Pragma,
+        ),
         map(
             terminated(space0::<&str, VerboseError<&str>>, preceded(opt(comment), line_ending)),
             |_| Line::Space,
@@ -643,7 +808,7 @@ fn line(input: &str) -> IResult<&str, Line, VerboseError<&str>> {
         ),
         map(
             delimited(space0, terminated(note, space0), preceded(opt(comment), line_ending)),
-            |n| Line::Note(n),
+            Line::Note,
         ),
         map(
             delimited(space0, terminated(skin_param, space0), preceded(opt(comment), line_ending)),
@@ -663,27 +828,38 @@ fn line(input: &str) -> IResult<&str, Line, VerboseError<&str>> {
         ),
         map(
             delimited(space0, terminated(item, space0), preceded(opt(comment), line_ending)),
-            |i| Line::Item(i),
+            Line::Item,
         ),
     ))(input)
 }
 
 pub fn mermaid(input: &str) -> IResult<&str, Diagram, VerboseError<&str>> {
     let mut context = Context::new();
-    let (input, lines) = alt((
-        delimited(startuml, preceded(multispace0, many0(terminated(line, multispace0))), enduml),
-        preceded(
-            preceded(opt(title), state_diagram_v2),
-            preceded(multispace0, many0(terminated(line, multispace0)))
+    let (input, (front_matter, lines)) = alt((
+        map(
+            delimited(startuml, preceded(multispace0, many0(terminated(line, multispace0))), enduml),
+            |lines| (None, lines),
         ),
-        map(multispace0, |_| vec![])
+        tuple((
+            terminated(opt(front_matter), state_diagram_v2),
+            preceded(multispace0, many0(terminated(line, multispace0))),
+        )),
+        map(multispace0, |_| (None, vec![]))
     ))(input)?;
     for line in lines {
-        if let Err(_) = context.process_line(line) {
+        if context.process_line(line).is_err() {
             return nom::error::context("unrecognized syntax", fail)(input);
         };
     }
-    let diagram = context.diagram();
+    let Ok(mut diagram) = context.diagram() else {
+        return nom::error::context("a rename directive names a state not in the diagram", fail)(
+            input,
+        );
+    };
+    if let Some(front_matter) = front_matter {
+        diagram.title = front_matter.title;
+        diagram.config = front_matter.config;
+    }
     Ok((input, diagram))
 }
 
@@ -708,21 +884,33 @@ enum Lexicon {
     TransitionNote(TransitionId, Vec<String>),
     Transition(TransitionId),
     FloatingNote(String),
+    EdgeRename(String, String),
+    Directive(Directive),
 }
 
 struct Context {
     frame_stack: Frames,
     scope: Scope,
     lex_log: Vec<Lexicon>,
+    /// Rename directives with the frames enclosing them, resolved once every state is known.
+    renames: Vec<(VecDeque<Frame>, VecDeque<Frame>, String)>,
 }
 
 impl Context {
-    fn diagram(self) -> Diagram {
+    fn diagram(self) -> anyhow::Result<Diagram> {
         let mut diagram: Diagram = Default::default();
+        let renames: Vec<_> = self
+            .renames
+            .into_iter()
+            .map(|(frame_stack, mut logical, name)| {
+                self.scope.resolve(&frame_stack, &mut logical);
+                (StateId(logical), name)
+            })
+            .collect();
         let frames_log = self.scope.flatten();
         for mut frames in frames_log {
             let frames = frames.make_contiguous();
-            if frames.len() > 0 {
+            if !frames.is_empty() {
                 let parent = StateId(VecDeque::new());
                 let child = StateId([frames[0].clone()].into());
                 diagram
@@ -791,9 +979,21 @@ impl Context {
                 Lexicon::FloatingNote(n) => {
                     diagram.note.push(n);
                 }
+                Lexicon::EdgeRename(l, n) => {
+                    diagram.edge_rename.insert(l, n);
+                }
+                Lexicon::Directive(d) => {
+                    diagram.directives.push(d);
+                }
             }
         }
-        diagram
+        for (state, name) in renames {
+            if !diagram.state_parent.contains_key(&state) {
+                bail!("no state {} to rename", state.display_name());
+            }
+            diagram.state_rename.insert(state, name);
+        }
+        Ok(diagram)
     }
     fn new() -> Self {
         Context {
@@ -802,6 +1002,7 @@ impl Context {
             },
             scope: Default::default(),
             lex_log: vec![],
+            renames: vec![],
         }
     }
 
@@ -820,11 +1021,32 @@ impl Context {
             Line::Direction => Ok(()),
             Line::Item(item) => self.process_item(item),
             Line::Note(note) => self.process_note(note),
+            Line::Pragma(pragma) => self.process_pragma(pragma),
         }
     }
 
+    fn process_pragma(&mut self, pragma: Pragma) -> anyhow::Result<()> {
+        match pragma {
+            Pragma::Rename { logical, name } => {
+                let logical = logical
+                    .into_iter()
+                    .map(|name| Frame::State { name })
+                    .collect();
+                self.renames
+                    .push((self.frame_stack.frames.clone(), logical, name));
+            }
+            Pragma::RenameEdge { label, name } => {
+                self.lex_log.push(Lexicon::EdgeRename(label, name));
+            }
+            Pragma::Directive(directive) => {
+                self.lex_log.push(Lexicon::Directive(directive));
+            }
+        }
+        Ok(())
+    }
+
     fn process_concurrent(&mut self) -> anyhow::Result<()> {
-        if self.frame_stack.frames.len() == 0 {
+        if self.frame_stack.frames.is_empty() {
             return Ok(());
         }
         let mut frames = self.frame_stack.frames.clone();
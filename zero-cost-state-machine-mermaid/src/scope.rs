@@ -14,10 +14,10 @@ impl Scope {
         let top_binding_point = frames.pop_front();
         if let Some(binding_point) = top_binding_point {
             let skip = match &binding_point {
-                Frame::Start => frame_stack.len() > 0,
-                Frame::End => frame_stack.len() > 0,
-                Frame::History => frame_stack.len() > 0,
-                Frame::DeepHistory => frame_stack.len() > 0,
+                Frame::Start => !frame_stack.is_empty(),
+                Frame::End => !frame_stack.is_empty(),
+                Frame::History => !frame_stack.is_empty(),
+                Frame::DeepHistory => !frame_stack.is_empty(),
                 _ => false,
             };
             match (skip, self.context_resume.get(&binding_point)) {
@@ -37,7 +37,7 @@ impl Scope {
         }
     }
     fn save_context(&mut self, frames: &[Frame]) {
-        if frames.len() == 0 {
+        if frames.is_empty() {
             return;
         }
         let (frames, bottom_binding_point) = frames.split_at(frames.len() - 1);
@@ -68,6 +68,10 @@ impl Scope {
         self.restore_context(frame_stack, frames);
         self.insert(frames.clone());
     }
+    /// Resolves `frames` as a state named within `frame_stack`, without declaring it.
+    pub fn resolve(&self, frame_stack: &VecDeque<Frame>, frames: &mut VecDeque<Frame>) {
+        self.restore_context(frame_stack, frames);
+    }
     pub fn flatten(self) -> Vec<VecDeque<Frame>> {
         self.frame_tree.flatten()
     }
@@ -1,10 +1,9 @@
 use crate::scope::Scope;
 use crate::scope::Tree;
 use crate::{tree, Frame};
-use internal::{frame, frames, Frames};
+use zero_cost_state_machine_ir::{frame, frames};
 use maplit::btreemap;
 use pretty_assertions::assert_eq;
-use std::collections::VecDeque;
 
 #[test]
 fn basic() {
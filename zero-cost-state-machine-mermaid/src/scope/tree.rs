@@ -49,9 +49,9 @@ impl Display for Tree {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         fn print_tree(tree: &Tree, f: &mut Formatter<'_>, depth: usize) -> std::fmt::Result {
             if tree.children.is_empty() {
-                return write!(f, "tree![],\n");
+                return writeln!(f, "tree![],");
             }
-            write!(f, "tree![\n")?;
+            writeln!(f, "tree![")?;
             let indent = "\t".repeat(depth + 1);
             for (k, v) in &tree.children {
                 write!(f, "{}", indent)?;
@@ -81,7 +81,7 @@ impl Tree {
         if let Some(f) = frames.pop_front() {
             self.children
                 .entry(f)
-                .or_insert(Default::default())
+                .or_default()
                 .insert(frames);
         }
     }
@@ -94,9 +94,9 @@ impl Tree {
             if tree.children.is_empty() {
                 log.push(stack.clone());
             }
-            for (k, mut v) in &mut tree.children {
+            for (k, v) in &mut tree.children {
                 stack.push_back(k.clone());
-                depth_first(&mut v, log, stack);
+                depth_first(v, log, stack);
             }
             stack.pop_back();
         }
@@ -1,10 +1,7 @@
 use crate::scope::Tree;
-use internal::frame;
-use internal::frames;
-use internal::Frame;
-use internal::Frames;
+use zero_cost_state_machine_ir::frames;
+use zero_cost_state_machine_ir::Frame;
 use pretty_assertions::assert_eq;
-use std::collections::VecDeque;
 
 #[test]
 fn basic() {
@@ -1,2 +1,14 @@
+use crate::{human_readable_error, mermaid, Diagram};
+
+mod builder;
+mod json;
 mod mermaid;
 mod plantuml;
+mod validate;
+
+/// Parses a whole diagram, failing if any of the text is left unparsed.
+fn parse(data: &str) -> anyhow::Result<Diagram> {
+    let (input, diagram) = human_readable_error(mermaid)(data)?;
+    assert!(input.is_empty());
+    Ok(diagram)
+}
@@ -0,0 +1,48 @@
+use crate::{human_readable_error, mermaid};
+use crate::{DiagramBuilder, StateId, StateStereoType};
+use pretty_assertions::assert_eq;
+
+#[test]
+fn matches_parser() -> anyhow::Result<()> {
+    let data = r#"
+            stateDiagram-v2
+            [*] --> State1
+            State1 --> State2 : Succeeded
+            State1 --> [*] : Aborted
+            State2 --> State3 : Succeeded
+            state State3 {
+              state "Accumulate Enough Data\nLong State Name" as long1
+              long1 : Just a test
+              [*] --> long1
+              long1 --> long1 : New Data
+              long1 --> [*]
+            }
+            State3 --> [*] : Aborted
+            state Check <<choice>>
+            note right of State2 : waits
+        "#;
+    let (input, parsed) = human_readable_error(mermaid)(data)?;
+    assert!(input.is_empty());
+
+    let root = StateId::root();
+    let state1 = root.child("State1");
+    let state2 = root.child("State2");
+    let state3 = root.child("State3");
+    let long1 = state3.child("long1");
+    let built = DiagramBuilder::new()
+        .transition(&root.start(), &state1, None)
+        .transition(&state1, &state2, Some("Succeeded"))
+        .transition(&state1, &root.end(), Some("Aborted"))
+        .transition(&state2, &state3, Some("Succeeded"))
+        .alias(&long1, "Accumulate Enough Data\nLong State Name")
+        .description(&long1, "Just a test")
+        .transition(&state3.start(), &long1, None)
+        .transition(&long1, &long1, Some("New Data"))
+        .transition(&long1, &state3.end(), None)
+        .transition(&state3, &root.end(), Some("Aborted"))
+        .stereotype(&root.child("Check"), StateStereoType::Choice)
+        .state_note(&state2, "waits")
+        .build();
+    assert_eq!(parsed, built);
+    Ok(())
+}
@@ -0,0 +1,86 @@
+use crate::{human_readable_error, mermaid};
+use crate::{Diagram, DiagramBuilder, StateId};
+use pretty_assertions::assert_eq;
+
+#[test]
+fn snapshot() -> anyhow::Result<()> {
+    let data = r#"
+            stateDiagram-v2
+            [*] --> Check
+            state Check <<choice>>
+            Check --> Busy : Go
+            state "Doing Work" as Busy
+            state Busy {
+              [*] --> work
+              work : Just a test
+            }
+            note right of Check : picks a branch
+        "#;
+    let (input, diagram) = human_readable_error(mermaid)(data)?;
+    assert!(input.is_empty());
+    assert_eq!(
+        serde_json::json!({
+            "states": [
+                { "id": ["start"] },
+                { "id": [{ "state": "Busy" }], "alias": "Doing Work", "composite": true },
+                { "id": [{ "state": "Busy" }, "start"] },
+                { "id": [{ "state": "Busy" }, { "state": "work" }], "description": ["Just a test"] },
+                { "id": [{ "state": "Check" }], "note": ["picks a branch"], "stereotype": "choice" }
+            ],
+            "transitions": [
+                { "from": ["start"], "to": [{ "state": "Check" }] },
+                { "from": [{ "state": "Busy" }, "start"], "to": [{ "state": "Busy" }, { "state": "work" }] },
+                { "from": [{ "state": "Check" }], "to": [{ "state": "Busy" }], "label": "Go" }
+            ],
+            "note": []
+        }),
+        serde_json::to_value(&diagram)?,
+    );
+    Ok(())
+}
+
+#[test]
+fn round_trip() -> anyhow::Result<()> {
+    let data = r#"
+            stateDiagram-v2
+            [*] --> State1
+            State1 --> State2 : Succeeded
+            State1 --> [*] : Aborted
+            State2 --> State3 : Succeeded
+            State2 --> [*] : Aborted
+            state State3 {
+              state "Accumulate Enough Data\nLong State Name" as long1
+              long1 : Just a test
+              [*] --> long1
+              long1 --> long1 : New Data
+              long1 --> ProcessData : Enough Data
+              long1 --> [*]
+            }
+            State3 --> State3 : Failed
+            State3 --> [*] : Succeeded / Save Result
+            State3 --> [*] : Aborted
+            state Active {
+              [*] --> NumLockOff
+              --
+              [*] --> CapsLockOff
+            }
+        "#;
+    let (input, diagram) = human_readable_error(mermaid)(data)?;
+    assert!(input.is_empty());
+    let json = serde_json::to_string(&diagram)?;
+    assert_eq!(diagram, serde_json::from_str::<Diagram>(&json)?);
+    Ok(())
+}
+
+#[test]
+fn empty_composite_round_trip() -> anyhow::Result<()> {
+    let root = StateId::root();
+    let diagram = DiagramBuilder::new()
+        .composite(&root.child("Outer").child("Inner"))
+        .build();
+    let json = serde_json::to_string(&diagram)?;
+    let read = serde_json::from_str::<Diagram>(&json)?;
+    assert!(read.is_composite(&root.child("Outer").child("Inner")));
+    assert_eq!(diagram, read);
+    Ok(())
+}
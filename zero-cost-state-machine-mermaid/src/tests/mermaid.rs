@@ -1,10 +1,8 @@
-use internal::{frame, frames};
-use internal::{state_id, transition_id};
+use zero_cost_state_machine_ir::{state_id, transition_id};
 use maplit::{btreemap, btreeset};
-use crate::{Frame, Frames, StateId, TransitionId, Diagram, StateStereoType};
-use std::collections::VecDeque;
+use crate::{Diagram, Directive, StateId, TransitionId};
 use pretty_assertions::assert_eq;
-use internal::StateStereoType::*;
+use zero_cost_state_machine_ir::StateStereoType::*;
 
 use crate::{human_readable_error, mermaid};
 #[test]
@@ -41,6 +39,7 @@ fn title() -> anyhow::Result<()> {
     assert!(input.is_empty());
     assert_eq!(
         Diagram {
+            title: Some("Foo".into()),
             ..Default::default()
         },
         diagram,
@@ -48,6 +47,76 @@ fn title() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn front_matter_config() -> anyhow::Result<()> {
+    let data = r#"
+            ---
+            title: "Simple example: state machine"
+            displayMode: compact
+            config:
+              theme: forest
+
+              zcsm:
+                lints: deny
+                payload:
+                  Saved: Vec<u8>
+              look: 'handDrawn'
+            ---
+            stateDiagram-v2
+            [*] --> [*]
+        "#;
+    let (input, diagram) = human_readable_error(mermaid)(data)?;
+    assert!(input.is_empty());
+    assert_eq!(Some("Simple example: state machine".to_string()), diagram.title);
+    assert_eq!(
+        btreemap! {
+            "look".to_string() => "handDrawn".to_string(),
+            "theme".to_string() => "forest".to_string(),
+            "zcsm.lints".to_string() => "deny".to_string(),
+            "zcsm.payload.Saved".to_string() => "Vec<u8>".to_string(),
+        },
+        diagram.config
+    );
+    Ok(())
+}
+
+#[test]
+fn front_matter_comments_and_lists() -> anyhow::Result<()> {
+    let data = r#"
+            ---
+            # The order flow of the shop
+            title: 'Order #1 flow' # shown as the heading
+            config:
+              zcsm:
+                # checked on every build
+                lints: deny # or warn
+                derive:
+                  - Debug
+                  - "Clone"
+                edges: [shared]
+              fontFamily: [Arial, 'Noto Sans']
+              layout:
+              - elk
+            ---
+            stateDiagram-v2
+            [*] --> [*]
+        "#;
+    let (input, diagram) = human_readable_error(mermaid)(data)?;
+    assert!(input.is_empty());
+    assert_eq!(Some("Order #1 flow".to_string()), diagram.title);
+    assert_eq!(
+        btreemap! {
+            "fontFamily".to_string() => "Arial, Noto Sans".to_string(),
+            "layout".to_string() => "elk".to_string(),
+            "zcsm.derive".to_string() => "Debug, Clone".to_string(),
+            "zcsm.edges".to_string() => "shared".to_string(),
+            "zcsm.lints".to_string() => "deny".to_string(),
+        },
+        diagram.config
+    );
+    Ok(())
+}
+
 #[test]
 fn comment() -> anyhow::Result<()> {
     let data = r#"
@@ -235,3 +304,110 @@ fn direction() -> anyhow::Result<()> {
     );
     Ok(())
 }
+
+#[test]
+fn rename() -> anyhow::Result<()> {
+    let data = r#"
+            stateDiagram-v2
+            [*] --> State3
+            state State3 {
+              state "Accumulate Enough Data" as long1
+              [*] --> long1
+              long1 --> [*]
+            }
+            State3 --> [*] : Succeeded / Save Result
+            %% @zcsm rename long1 = Accumulating
+            %%@zcsm rename-edge "Succeeded / Save Result" = Saved
+        "#;
+    let (input, diagram) = human_readable_error(mermaid)(data)?;
+    assert!(input.is_empty());
+    let long1 = state_id!["State3", "long1"];
+    assert_eq!(
+        btreemap! {long1.clone() => "Accumulating".to_string()},
+        diagram.state_rename
+    );
+    assert_eq!(
+        btreemap! {"Succeeded / Save Result".to_string() => "Saved".to_string()},
+        diagram.edge_rename
+    );
+    assert_eq!(vec!["state3", "node", "Accumulating"], diagram.type_path(&long1));
+    assert_eq!(
+        Some("Saved".to_string()),
+        diagram.edge_name(&TransitionId(
+            state_id!["State3"],
+            StateId::root().end(),
+            Some("Succeeded / Save Result".into())
+        ))
+    );
+    Ok(())
+}
+
+#[test]
+fn rename_before_declaration() -> anyhow::Result<()> {
+    let data = r#"
+            stateDiagram-v2
+            %% @zcsm rename long1 = Accumulating
+            %% @zcsm rename State3 = Busy
+            [*] --> State3
+            state State3 {
+              [*] --> long1
+              long1 --> [*]
+            }
+            State3 --> [*]
+        "#;
+    let (input, diagram) = human_readable_error(mermaid)(data)?;
+    assert!(input.is_empty());
+    assert_eq!(
+        btreemap! {
+            state_id!["State3"] => "Busy".to_string(),
+            state_id!["State3", "long1"] => "Accumulating".to_string(),
+        },
+        diagram.state_rename
+    );
+    assert!(!diagram.state_parent.contains_key(&state_id!["long1"]));
+    assert_eq!(
+        Some(&btreeset! {state_id!["State3", Start], state_id!["State3", "long1"], state_id!["State3", End]}),
+        diagram.state_children.get(&state_id!["State3"])
+    );
+    Ok(())
+}
+
+#[test]
+fn rename_unknown_state() {
+    let data = r#"
+            stateDiagram-v2
+            %% @zcsm rename long2 = Accumulating
+            [*] --> long1
+        "#;
+    let error = human_readable_error(mermaid)(data).err().unwrap().to_string();
+    assert!(error.contains("a rename directive names a state not in the diagram"));
+}
+
+#[test]
+fn directives() -> anyhow::Result<()> {
+    let data = r#"
+            stateDiagram-v2
+            %% @zcsm derive Debug, Clone
+            %% @zcsm lints deny
+            %% @zcsm payload Saved = Vec<u8>   
+            %% @zcsm flag
+            %% @zcsm-not-a-directive
+            [*] --> [*] : Saved
+        "#;
+    let (input, diagram) = human_readable_error(mermaid)(data)?;
+    assert!(input.is_empty());
+    let directive = |key: &str, value: &str| Directive {
+        key: key.into(),
+        value: value.into(),
+    };
+    assert_eq!(
+        vec![
+            directive("derive", "Debug, Clone"),
+            directive("lints", "deny"),
+            directive("payload", "Saved = Vec<u8>"),
+            directive("flag", ""),
+        ],
+        diagram.directives
+    );
+    Ok(())
+}
@@ -1,8 +1,6 @@
-use internal::{frame, frames};
-use internal::{state_id, transition_id};
+use zero_cost_state_machine_ir::{state_id, transition_id};
 use maplit::{btreemap, btreeset};
-use crate::{Frame, Frames, StateId, TransitionId, Diagram, StateStereoType};
-use std::collections::VecDeque;
+use crate::{Diagram, StateStereoType};
 use pretty_assertions::assert_eq;
 
 use crate::{human_readable_error, mermaid};
@@ -0,0 +1,256 @@
+use super::parse;
+use crate::{Diagram, Rule};
+use zero_cost_state_machine_ir::{state_id, transition_id};
+use pretty_assertions::assert_eq;
+
+fn rules(diagram: &Diagram) -> Vec<(Rule, String)> {
+    diagram
+        .validate()
+        .into_iter()
+        .map(|d| (d.rule, d.message))
+        .collect()
+}
+
+#[test]
+fn readme() -> anyhow::Result<()> {
+    let diagram = parse(
+        r#"
+            stateDiagram-v2
+            [*] --> State1
+            State1 --> State2 : Succeeded
+            State1 --> [*] : Aborted
+            State2 --> State3 : Succeeded
+            State2 --> [*] : Aborted
+            state State3 {
+              state "Accumulate Enough Data\nLong State Name" as long1
+              long1 : Just a test
+              [*] --> long1
+              long1 --> long1 : New Data
+              long1 --> ProcessData : Enough Data
+              long1 --> [*]
+            }
+            State3 --> State3 : Failed
+            State3 --> [*] : Succeeded / Save Result
+            State3 --> [*] : Aborted
+        "#,
+    )?;
+    let diagnostics = diagram.validate();
+    assert!(!diagnostics.iter().any(|d| d.is_error()));
+    assert_eq!(
+        vec![(
+            Rule::CannotReachEnd,
+            "state State3.ProcessData has no path to the final state".to_string()
+        )],
+        rules(&diagram)
+    );
+    assert_eq!(vec![state_id!["State3", "ProcessData"]], diagnostics[0].states);
+    Ok(())
+}
+
+#[test]
+fn composite_entry_and_exit() -> anyhow::Result<()> {
+    let diagram = parse(
+        r#"
+            stateDiagram-v2
+            A --> B
+            B --> C
+            state B {
+              x --> y
+            }
+        "#,
+    )?;
+    assert_eq!(
+        vec![
+            (Rule::MissingStart, "state B must contain Start state".to_string()),
+            (Rule::MissingEnd, "state B must contain an End state".to_string()),
+        ],
+        rules(&diagram)
+    );
+    Ok(())
+}
+
+#[test]
+fn name_collisions() -> anyhow::Result<()> {
+    let diagram = parse(
+        r#"
+            stateDiagram-v2
+            [*] --> long1
+            long1 --> Long1 : go
+            long1 --> Long1 : Go
+            Long1 --> [*]
+        "#,
+    )?;
+    assert_eq!(
+        vec![
+            (
+                Rule::NodeNameCollision,
+                "the special state Root contains multiple child states which when converted to upper camel case are Long1".to_string()
+            ),
+            (
+                Rule::EdgeNameCollision,
+                "multiple transitions are exiting from state long1 which when converted to upper camel case are Go".to_string()
+            ),
+        ],
+        rules(&diagram)
+    );
+    Ok(())
+}
+
+#[test]
+fn unreachable_composite_is_reported_once() -> anyhow::Result<()> {
+    let diagram = parse(
+        r#"
+            stateDiagram-v2
+            [*] --> A
+            A --> [*]
+            state B {
+              [*] --> x
+              x --> [*]
+            }
+            B --> A
+            state "Same" as A
+            state "Same" as C
+        "#,
+    )?;
+    assert_eq!(
+        vec![
+            (
+                Rule::Unreachable,
+                "state B cannot be reached from the initial state".to_string()
+            ),
+            (
+                Rule::Unreachable,
+                "state C cannot be reached from the initial state".to_string()
+            ),
+            (
+                Rule::CannotReachEnd,
+                "state C has no path to the final state".to_string()
+            ),
+            (
+                Rule::DuplicateAlias,
+                "states A and C share the alias \"Same\"".to_string()
+            ),
+        ],
+        rules(&diagram)
+    );
+    Ok(())
+}
+
+#[test]
+fn never_exited() -> anyhow::Result<()> {
+    let diagram = parse(
+        r#"
+            stateDiagram-v2
+            [*] --> Busy
+            Busy --> [*] : Done
+            state Busy {
+              [*] --> spinning
+              spinning --> spinning : Tick
+              spinning --> [*] : Stop
+            }
+            Busy --> Busy : Retry
+        "#,
+    )?;
+    assert_eq!(Vec::<(Rule, String)>::new(), rules(&diagram));
+    let trapped = parse(
+        r#"
+            stateDiagram-v2
+            [*] --> Busy
+            Busy --> [*] : Done
+            state Busy {
+              [*] --> spinning
+              spinning --> spinning : Tick
+              state finished
+              finished --> [*]
+            }
+        "#,
+    )?;
+    assert_eq!(
+        vec![
+            (
+                Rule::Unreachable,
+                "state [*] cannot be reached from the initial state".to_string()
+            ),
+            (
+                Rule::Unreachable,
+                "state Busy.[*] cannot be reached from the initial state".to_string()
+            ),
+            (
+                Rule::Unreachable,
+                "state Busy.finished cannot be reached from the initial state".to_string()
+            ),
+            (
+                Rule::NeverExited,
+                "state Busy is entered but can never be exited".to_string()
+            ),
+            (
+                Rule::DeadTransition,
+                "transition Busy --> [*] : Done can never fire".to_string()
+            ),
+            (
+                Rule::CannotReachEnd,
+                "state [*] has no path to the final state".to_string()
+            ),
+            (
+                Rule::CannotReachEnd,
+                "state Busy.[*] has no path to the final state".to_string()
+            ),
+            (
+                Rule::CannotReachEnd,
+                "state Busy.spinning has no path to the final state".to_string()
+            ),
+        ],
+        rules(&trapped)
+    );
+    Ok(())
+}
+
+#[test]
+fn ambiguous_completion() -> anyhow::Result<()> {
+    let diagram = parse(
+        r#"
+            stateDiagram-v2
+            [*] --> Work
+            Work --> Done
+            Work --> Failed
+            Done --> [*]
+            Failed --> [*]
+        "#,
+    )?;
+    assert_eq!(
+        vec![(
+            Rule::AmbiguousCompletion,
+            "state Work has multiple unlabelled transitions, to Done and Failed; label one of them"
+                .to_string()
+        )],
+        rules(&diagram)
+    );
+    Ok(())
+}
+
+#[test]
+fn nondeterministic_transitions() -> anyhow::Result<()> {
+    let diagram = parse(
+        r#"
+            stateDiagram-v2
+            [*] --> Waiting
+            Waiting --> Retrying : Timeout
+            Waiting --> Failed : Timeout
+            Waiting --> [*] : Reply
+            Retrying --> Waiting : Sent
+            Failed --> [*]
+        "#,
+    )?;
+    assert_eq!(Vec::<(Rule, String)>::new(), rules(&diagram));
+    assert_eq!(
+        vec![
+            &transition_id! {["Waiting"]->["Failed"]:"Timeout"},
+            &transition_id! {["Waiting"]->["Retrying"]:"Timeout"},
+        ],
+        diagram
+            .nondeterministic_transitions()
+            .into_iter()
+            .collect::<Vec<_>>()
+    );
+    Ok(())
+}
@@ -0,0 +1,94 @@
+#![no_std]
+
+// generates `pub mod blinky`, named after the title
+zero_cost_state_machine_macro::statemachine_from_mermaid! {
+    r#"
+        ---
+        title: Blinky
+        ---
+        stateDiagram-v2
+        %% @zcsm derive Debug, Clone, Copy, PartialEq
+        %% @zcsm payload Dim = u8
+        [*] --> Off
+        Off --> On : Press
+        On --> Off : Press
+        On --> Dimmed : Dim
+        Dimmed --> Off : Press
+        Dimmed --> [*] : Fault
+        state Dimmed {
+          [*] --> Low
+          Low --> High : Press
+          Low --> [*] : Timeout
+          High --> [*] : Timeout
+        }
+    "#,
+    backend = table
+}
+
+pub mod button {
+    zero_cost_state_machine_macro::statemachine_from_mermaid! {
+        r#"
+            stateDiagram-v2
+            [*] --> Idle
+            Idle --> Short : Release
+            Idle --> Long : Release
+            Short --> [*]
+            Long --> [*]
+        "#
+    }
+}
+
+use zero_cost_state_machine::{Mermaid, Path, StateInfo, TryPath};
+
+/// Drives `blinky` through its typestate API, returning the dense id it ends in.
+pub fn blink(level: u8) -> blinky::dense::StateId {
+    let on = blinky::State {
+        head: blinky::node::Start,
+    }
+    .transition(())
+    .transition(blinky::edge::Press);
+    let dimmed = on.transition(blinky::edge::Dim(level));
+    dimmed.transition(()).id()
+}
+
+/// Drives `blinky` from its dense table alone.
+pub fn blink_dynamic(events: &[blinky::dense::EventId]) -> blinky::dense::StateId {
+    let mut current = blinky::dense::StateId::Start;
+    for event in events {
+        current = current.next(*event).unwrap_or(current);
+    }
+    current
+}
+
+/// Tells a short press from a long one with the guards of `button`.
+pub fn press(held_ms: u32) -> &'static str {
+    let idle = button::State {
+        head: button::node::Start,
+    }
+    .transition(());
+    match idle.try_transition(button::edge::Release, button::node::Short, held_ms < 500) {
+        Ok(short) => short_name(&short),
+        Err(idle) => match idle.try_transition(button::edge::Release, button::node::Long, true) {
+            Ok(long) => long_name(&long),
+            Err(_) => "",
+        },
+    }
+}
+
+fn short_name<S: StateInfo>(_: &button::State<S>) -> &'static str {
+    S::NAME
+}
+
+fn long_name<S: StateInfo>(_: &button::State<S>) -> &'static str {
+    S::NAME
+}
+
+/// The `blinky` diagram with `node::On` highlighted, formatted without allocating.
+pub fn show_on(out: &mut impl core::fmt::Write) -> core::fmt::Result {
+    write!(
+        out,
+        "{}",
+        zero_cost_state_machine::to_mermaid::<blinky::node::On>(blinky::DIAGRAM)
+    )?;
+    write!(out, "{}", Mermaid::new(blinky::DIAGRAM))
+}
@@ -0,0 +1,31 @@
+use zero_cost_state_machine_no_std::blinky::dense::{EventId, StateId};
+use zero_cost_state_machine_no_std::{blink, blink_dynamic, press, show_on};
+
+#[test]
+fn typestate() {
+    assert_eq!(StateId::DimmedLow, blink(40));
+    assert_eq!("Short", press(100));
+    assert_eq!("Long", press(900));
+}
+
+#[test]
+fn table() {
+    let events = [
+        EventId::Completion,
+        EventId::Press,
+        EventId::Dim,
+        EventId::Completion,
+    ];
+    assert_eq!(blink(40), blink_dynamic(&events));
+    assert_eq!(
+        StateId::Off,
+        blink_dynamic(&[EventId::Completion, EventId::DimmedFault])
+    );
+}
+
+#[test]
+fn mermaid() {
+    let mut shown = String::new();
+    show_on(&mut shown).unwrap();
+    assert!(shown.contains("    class On active\n"));
+}
@@ -1,8 +1,215 @@
+#![no_std]
+
 pub trait Path<P> {
     type Target;
     fn transition(self, path: P) -> Self::Target;
 }
 
+/// One of several transitions taken with the same event `P`, the one leading to node `D`.
+/// It is only taken if `guard` holds; otherwise the state is handed back.
+pub trait TryPath<D, P>: Sized {
+    type Target;
+    fn try_transition(self, path: P, to: D, guard: bool) -> Result<Self::Target, Self>;
+}
+
 pub struct NoEdge;
 
 pub struct NoNode;
+
+/// One level of how a nested `State` was entered: the node left and the edge taken to
+/// enter a composite, and the levels enclosing it, ending in `()`. Where a transition
+/// entered several composites at once, the inner ones record `NoNode` and `NoEdge`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Crumb<'a, N, E, P> {
+    pub from: &'a N,
+    pub edge: &'a E,
+    pub parent: P,
+}
+
+impl<N, E, P: Clone> Clone for Crumb<'_, N, E, P> {
+    fn clone(&self) -> Self {
+        Crumb {
+            from: self.from,
+            edge: self.edge,
+            parent: self.parent.clone(),
+        }
+    }
+}
+
+impl<N, E, P: Copy> Copy for Crumb<'_, N, E, P> {}
+
+/// The entry path of a nested `State`, innermost composite first.
+pub trait Breadcrumbs {
+    /// The node left to enter the innermost composite.
+    type From;
+    /// The edge taken to enter the innermost composite.
+    type Edge;
+    /// The whole entry path as a list of [`Crumb`]s.
+    type Ancestry<'a>
+    where
+        Self: 'a;
+    /// The entry path of the enclosing composite, or `()` at the outermost one.
+    type Parent<'a>
+    where
+        Self: 'a;
+
+    fn entered_from(&self) -> &Self::From;
+    fn entry_edge(&self) -> &Self::Edge;
+    fn ancestry(&self) -> Self::Ancestry<'_>;
+    fn parent(&self) -> Self::Parent<'_>;
+}
+
+impl<'c, N, E, P: Copy> Breadcrumbs for Crumb<'c, N, E, P> {
+    type From = N;
+    type Edge = E;
+    type Ancestry<'a>
+        = Self
+    where
+        Self: 'a;
+    type Parent<'a>
+        = P
+    where
+        Self: 'a;
+
+    fn entered_from(&self) -> &N {
+        self.from
+    }
+    fn entry_edge(&self) -> &E {
+        self.edge
+    }
+    fn ancestry(&self) -> Self {
+        *self
+    }
+    fn parent(&self) -> P {
+        self.parent
+    }
+}
+
+/// Implemented by a `State` from which the node `T`, or a state inside the composite `T`,
+/// can be reached by some sequence of transitions, including none.
+pub trait CanReach<T> {}
+
+/// What the diagram says about a `node` type.
+pub trait StateInfo {
+    /// The name of the `node` type, e.g. `Long1`.
+    const NAME: &'static str;
+    /// The name displayed for the state, if it is given one with `state "..." as name`.
+    const ALIAS: Option<&'static str>;
+    /// The state and the composites enclosing it as written in the diagram, outermost
+    /// first, e.g. `["State3", "long1"]`.
+    const PATH: &'static [&'static str];
+    /// The number of composites enclosing the state.
+    const DEPTH: usize;
+    const IS_START: bool;
+    const IS_END: bool;
+    /// The `state : description` lines of the state.
+    const DESCRIPTION: &'static [&'static str];
+}
+
+/// What the diagram says about an `edge` type.
+pub trait EdgeInfo {
+    /// The label of the transitions taking the edge; empty for an unlabelled transition.
+    const LABEL: &'static str;
+    /// The notes attached to the transitions taking the edge.
+    const NOTE: &'static [&'static str];
+}
+
+impl EdgeInfo for () {
+    const LABEL: &'static str = "";
+    const NOTE: &'static [&'static str] = &[];
+}
+
+/// The generated `STATES`, `TRANSITIONS` and `CYCLES` tables, describing the flattened machine.
+pub mod table {
+    /// A state that can be the head of a `State`.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct StateInfo {
+        /// The `node` type as a path from the machine root, e.g. `state3::node::Long1`.
+        pub node: &'static str,
+        /// The state and the composites enclosing it as written in the diagram, outermost
+        /// first.
+        pub path: &'static [&'static str],
+        pub alias: Option<&'static str>,
+        /// The number of composites enclosing the state.
+        pub depth: usize,
+        pub is_start: bool,
+        pub is_end: bool,
+        pub description: &'static [&'static str],
+    }
+
+    /// A transition, from the `End` of a composite it leaves and to the `Start` of a
+    /// composite it enters.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct TransitionInfo {
+        /// The `node` type the transition leaves, as in [`StateInfo::node`].
+        pub from: &'static str,
+        /// The `node` type the transition enters, as in [`StateInfo::node`].
+        pub to: &'static str,
+        /// The label as written in the diagram.
+        pub label: Option<&'static str>,
+        /// The `edge` type taken, as a path from the machine root, or `()`.
+        pub edge: &'static str,
+        /// Whether the transition shares its edge with others out of the same state, and is
+        /// taken through `TryPath`.
+        pub guarded: bool,
+    }
+
+    /// A set of states the machine can move between any number of times.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct CycleInfo {
+        /// The `node` types of the states on the cycle, as in [`StateInfo::node`], leaving
+        /// out the `[*]` pseudo-states.
+        pub states: &'static [&'static str],
+        /// Whether the machine leaves the cycle for its final state and no state on it
+        /// transitions back to itself. Code looping through an unbounded cycle should not
+        /// recurse, or it may overflow the stack.
+        pub bounded: bool,
+    }
+}
+
+/// A Mermaid diagram, displayed with one of its states marked by a `classDef active` class.
+///
+/// ```
+/// use zero_cost_state_machine::Mermaid;
+/// let diagram = "stateDiagram-v2\n    [*] --> Idle\n";
+/// let shown = Mermaid::new(diagram).active(&["Idle"]).to_string();
+/// assert!(shown.ends_with("    class Idle active\n"));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Mermaid<'a> {
+    diagram: &'a str,
+    active: Option<&'a str>,
+}
+
+impl<'a> Mermaid<'a> {
+    pub fn new(diagram: &'a str) -> Self {
+        Mermaid {
+            diagram,
+            active: None,
+        }
+    }
+
+    /// Marks the state at `path`, as in [`StateInfo::PATH`]. Start, End and history states
+    /// cannot be styled in Mermaid, so they leave the diagram unmarked.
+    pub fn active(self, path: &[&'a str]) -> Self {
+        let active = path.last().copied().filter(|id| !id.starts_with('['));
+        Mermaid { active, ..self }
+    }
+}
+
+impl core::fmt::Display for Mermaid<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.diagram.trim_end())?;
+        f.write_str("\n")?;
+        if let Some(id) = self.active {
+            f.write_str("    classDef active fill:#f96,stroke:#333,stroke-width:2px\n")?;
+            writeln!(f, "    class {} active", id)?;
+        }
+        Ok(())
+    }
+}
+
+/// The diagram with the state of `node` type `S` marked.
+pub fn to_mermaid<S: StateInfo>(diagram: &str) -> Mermaid<'_> {
+    Mermaid::new(diagram).active(S::PATH)
+}